@@ -25,7 +25,7 @@ fn parse_reshape(input: &mut Lexer) -> Result<tree::ReshapeOptions, String> {
     match input.next() {
         Some((lx, ln, cl)) => if lx == "new" {
             let newattr = attr::parse(input)?;
-            if let None = newattr.default {
+            if newattr.default.is_none() && newattr.flag != tree::AttrFlag::Global {
                 return Err(format!["New attribute '{}' in reshape doesn't have a default value at ({}, {}).", newattr.name, ln, cl])
             }
             Ok(tree::ReshapeOptions::New(newattr))
@@ -39,6 +39,8 @@ fn parse_reshape(input: &mut Lexer) -> Result<tree::ReshapeOptions, String> {
 }
 
 impl attr {
+    // [computable | global | unique] name: Type [as lambda]; computable attributes need the lambda and globals
+    // take none, as their value is given later by 'set E.name { expr }'.
     fn parse(input: &mut Lexer) -> Result<attr, String> {
         let mut res = attr::new();
         let lexeme;
@@ -57,15 +59,11 @@ impl attr {
             res.flag = tree::AttrFlag::Unique;
         }
         res.name = read_ident(input)?;
-        if lexeme != "global" {
-            expect(input, vec![":"])?;
-            res.attrType = tree::SpType::parse_type(input)?;
-            let lex = input.lookahead();
-            if res.flag == tree::AttrFlag::Computable || lex == Some("as".to_string()) {
-                expect(input, vec!["as"])?;
-                res.default = Some(tree::Lambda::parse_lambda(input)?);
-            }
-        } else {
+        expect(input, vec![":"])?;
+        res.attrType = tree::SpType::parse_type(input)?;
+        let lex = input.lookahead();
+        if res.flag == tree::AttrFlag::Computable || res.flag != tree::AttrFlag::Global && lex == Some("as".to_string()) {
+            expect(input, vec!["as"])?;
             res.default = Some(tree::Lambda::parse_lambda(input)?);
         }
         return Ok(res);
@@ -222,7 +220,40 @@ impl Cmd {
                 }
             }
             expect(input, vec!["as"])?;
-            value = Cmd::Product(res, read_ident(input)?);
+            let name = read_ident(input)?;
+            let mut limit = tree::DEFAULT_PRODUCT_LIMIT;
+            if input.lookahead() == Some("limit".to_string()) {
+                expect(input, vec!["limit"])?;
+                let (lx, ln, cl) = input.next().ok_or("Expected row limit, found EOF.")?;
+                limit = lx.parse::<u64>().map_err(|_| format!["Expected row limit, found '{}' at ({}, {}).", lx, ln, cl])?;
+            }
+            value = Cmd::Product(res, name, limit);
+        } else if lexeme == "set" {
+            let name = read_ident(input)?;
+            expect(input, vec!["."])?;
+            let attr = read_ident(input)?;
+            expect(input, vec!["{"])?;
+            let (br, br1, br2) = find_bracket(input, "}", "{")?;
+            value = Cmd::SetGlobal(name, attr, tree::Node::new(br, br1, br2)?);
+        } else if lexeme == "type" {
+            let name = read_ident(input)?;
+            expect(input, vec!["="])?;
+            value = Cmd::TypeAlias(name, tree::SpType::parse_type(input)?);
+        } else if lexeme == "def" {
+            let (ln, cl) = input.coords();
+            let lm = tree::Lambda::parse_lambda(input)?;
+            if lm.named.is_none() {
+                return Err(format!["Expected function name at ({}, {}).", ln, cl]);
+            }
+            value = Cmd::Def(lm);
+        } else if lexeme == "union" || lexeme == "intersect" || lexeme == "minus" {
+            let op = if lexeme == "union" {tree::SetOperation::Union} else if lexeme == "intersect"
+                {tree::SetOperation::Intersect} else {tree::SetOperation::Minus};
+            let ent1 = read_ident(input)?;
+            expect(input, vec![","])?;
+            let ent2 = read_ident(input)?;
+            expect(input, vec!["as"])?;
+            value = Cmd::SetOp(op, ent1, ent2, read_ident(input)?);
         } else if lexeme == "drop" {
             value = Cmd::Drop(read_ident(input)?);
         } else {
@@ -1,9 +1,52 @@
-use std::collections::HashMap;
-use crate::tree::{self, ExprTree};
+use std::{cmp::Ordering, collections::HashMap};
+use crate::{stdlib, tree::{self, ExprTree}};
 
 fn comp<T: std::cmp::PartialOrd>(g: &bool, ns: &bool, e1: &T, e2: &T) -> bool {
     if *g {if *ns {*e1 >= *e2} else {*e1 > *e2}} else {if *ns {*e1 <= *e2} else {*e1 < *e2}}
 }
+// Lexicographic order of arrays and tuples, with elements compared by '<'.
+fn seq_cmp(v1: &Vec<Box<tree::Node>>, v2: &Vec<Box<tree::Node>>, db: &tree::DBState, params: &HashMap<String, tree::ExprTree>) -> Result<Ordering, String> {
+    for (x, y) in v1.iter().zip(v2.iter()) {
+        if let ExprTree::BoolLit(true) = compute(&ExprTree::Cmp(false, false, x.clone(), y.clone()), db, params)? {
+            return Ok(Ordering::Less);
+        }
+        if let ExprTree::BoolLit(true) = compute(&ExprTree::Cmp(false, false, y.clone(), x.clone()), db, params)? {
+            return Ok(Ordering::Greater);
+        }
+    }
+    Ok(v1.len().cmp(&v2.len()))
+}
+// Applies a lambda over objects to the given (entity position, row) pairs.
+fn row_apply(lm: &tree::Lambda, rows: &[(usize, u64)], db: &tree::DBState, params: &HashMap<String, tree::ExprTree>) -> Result<ExprTree, String> {
+    let mut new_params = params.clone();
+    for ((name, _), (pos, row)) in lm.params.iter().zip(rows.iter()) {
+        new_params.insert(name.clone(), ExprTree::Ref(String::new(), String::new(), Box::new(
+            tree::Node::simple(ExprTree::TupleLit(vec![]))), *pos as u64, *row));
+    }
+    compute(&lm.code.tree, db, &new_params)
+}
+// Moves to the next combination of rows, the last entity varying fastest; false when all were visited.
+fn next_rows(rows: &mut Vec<(usize, u64)>, counts: &Vec<u64>) -> bool {
+    for k in (0..rows.len()).rev() {
+        rows[k].1 += 1;
+        if rows[k].1 < counts[k] {
+            return true;
+        }
+        rows[k].1 = 0;
+    }
+    false
+}
+// Folds the next value of a group into the accumulator of the aggregate.
+fn aggregate_step(agg: &str, acc: ExprTree, v: ExprTree, db: &tree::DBState, params: &HashMap<String, tree::ExprTree>) -> Result<ExprTree, String> {
+    let (acc, v) = (Box::new(tree::Node::simple(acc)), Box::new(tree::Node::simple(v)));
+    match agg {
+        "sum" | "avg" => compute(&ExprTree::Plus(acc, v), db, params),
+        _ => match compute(&ExprTree::Cmp(agg == "max", false, v.clone(), acc.clone()), db, params)? {
+            ExprTree::BoolLit(true) => Ok(v.tree),
+            _ => Ok(acc.tree)
+        }
+    }
+}
 fn int_double_op(r1: &ExprTree, r2: &ExprTree, op: char) -> Result<ExprTree, String> {
     match r1 {
         ExprTree::IntLit(v) => {
@@ -29,14 +72,71 @@ fn int_double_op(r1: &ExprTree, r2: &ExprTree, op: char) -> Result<ExprTree, Str
     }
 }
 
+fn is_std(name: &str, db: &tree::DBState, params: &HashMap<String, tree::ExprTree>) -> bool {
+    name == "Std" && !db.header.iter().any(|(x, _)| x == "Std") && !params.contains_key("Std")
+}
+
+pub fn apply(f: &ExprTree, args: Vec<ExprTree>, db: &tree::DBState, params: &HashMap<String, tree::ExprTree>) -> Result<ExprTree, String> {
+    match f {
+        // A lambda that was never evaluated to a closure is closed, e.g. a 'def' function or an attribute lambda.
+        ExprTree::LambdaExpr(lm) => apply(&ExprTree::Closure(lm.clone(), vec![]), args, db, params),
+        ExprTree::Closure(lm, env) => {
+            if lm.params.len() != args.len() {
+                return Err("Wrong number of arguments in call".to_string());
+            }
+            let mut new_params: HashMap<String, ExprTree> = env.iter().map(|(n, v)| (n.clone(), v.tree.clone())).collect();
+            if let Some((n, _)) = &lm.named {
+                new_params.insert(n.clone(), f.clone());
+            }
+            for (i, a) in args.into_iter().enumerate() {
+                new_params.insert(lm.params[i].0.clone(), a);
+            }
+            compute(&lm.code.tree, db, &new_params)
+        }, ExprTree::StdFunc(n) => stdlib::call(n, args, db, params),
+        _ => Err("Unable to call not a function".to_string())
+    }
+}
+
+// Empty array values always carry their element type, the others may leave it to their elements.
+pub fn elem_type(v: &Vec<Box<tree::Node>>, t: &Option<tree::Type>, db: &tree::DBState) -> Result<tree::Type, String> {
+    match (t, v.get(0)) {
+        (Some(t), _) => Ok(t.clone()),
+        (None, Some(x)) => x.as_ref().clone().get_type(db, &HashMap::new()),
+        (None, None) => Err("Empty array without an element type".to_string())
+    }
+}
+
+pub fn value_has_type(val: &ExprTree, t: &tree::Type, db: &tree::DBState) -> bool {
+    match (val, t) {
+        (ExprTree::IntLit(_), tree::Type::Int) | (ExprTree::CharLit(_), tree::Type::Char) |
+            (ExprTree::BoolLit(_), tree::Type::Bool) | (ExprTree::DoubleLit(_), tree::Type::Double) => true,
+        (ExprTree::ArrayLit(v, _), tree::Type::Array(et)) => v.iter().all(|x| value_has_type(&x.tree, et, db)),
+        (ExprTree::TupleLit(v), tree::Type::Tuple(ts)) => v.len() == ts.len() &&
+            v.iter().zip(ts.iter()).all(|(x, et)| value_has_type(&x.tree, et, db)),
+        (ExprTree::RecordLit(v), tree::Type::Record(ts)) => v.len() == ts.len() &&
+            v.iter().zip(ts.iter()).all(|((n, x), (tn, et))| n == tn && value_has_type(&x.tree, et, db)),
+        (ExprTree::JustLit(v), tree::Type::Maybe(et)) => value_has_type(&v.tree, et, db),
+        (ExprTree::NothingLit(nt), tree::Type::Maybe(et)) => *nt == **et,
+        (ExprTree::Ref(_, _, _, ent, _), tree::Type::Object(name)) => db.header.get(*ent as usize).map_or(false, |x| x.0 == name.name),
+        (ExprTree::LambdaExpr(lm), tree::Type::FuncType(_)) => lm.clone().get_type(db, &HashMap::new()).map_or(false, |x| x == *t),
+        (ExprTree::Closure(_, _), tree::Type::FuncType(_)) => tree::Node::simple(val.clone()).get_type(db, &HashMap::new()).map_or(false, |x| x == *t),
+        (ExprTree::StdFunc(n), tree::Type::FuncType(_)) => stdlib::value_type(n).as_ref() == Some(t),
+        (ExprTree::SumVal(st, _, _), _) => *st == *t,
+        (_, tree::Type::Sum(v)) => v.iter().any(|x| value_has_type(val, x, db)),
+        _ => false
+    }
+}
+
 pub fn find_refs(expr: &ExprTree) -> Vec<(u64, u64)> {
     match expr {
         ExprTree::ArrayLit(v, _) | ExprTree::TupleLit(v) => v.iter().map(|x| find_refs(&x.tree)).flatten().collect(),
         ExprTree::Ref(_, _, _, u1, u2) => vec![(*u1, *u2)],
-        ExprTree::JustLit(v) => find_refs(&v.tree),
+        ExprTree::RecordLit(v) => v.iter().map(|(_, x)| find_refs(&x.tree)).flatten().collect(),
+        ExprTree::JustLit(v) | ExprTree::SumVal(_, _, v) => find_refs(&v.tree),
         ExprTree::Plus(u, v) | ExprTree::Minus(u, v) | ExprTree::Div(u, v) |
             ExprTree::Mod(u, v) | ExprTree::Dot(u, v) | ExprTree::Mul(u, v) |
-            ExprTree::Exp(u, v) | ExprTree::Eq(_, u, v) | ExprTree::Cmp(_, _, u, v) => {
+            ExprTree::Exp(u, v) | ExprTree::Eq(_, u, v) | ExprTree::Cmp(_, _, u, v) |
+            ExprTree::And(u, v) | ExprTree::Or(u, v) | ExprTree::Index(u, v) => {
                 let mut temp = find_refs(&u.tree);
                 temp.append(&mut find_refs(&v.tree));
                 temp
@@ -50,6 +150,29 @@ pub fn find_refs(expr: &ExprTree) -> Vec<(u64, u64)> {
                 temp.append(&mut find_refs(&c.tree));
                 temp
             }, ExprTree::LambdaExpr(lm) => find_refs(&lm.code.tree),
+            ExprTree::Closure(lm, env) => {
+                let mut temp: Vec<_> = env.iter().map(|x| find_refs(&x.1.tree)).flatten().collect();
+                temp.append(&mut find_refs(&lm.code.tree));
+                temp
+            },
+            ExprTree::Not(v) | ExprTree::Neg(v) => find_refs(&v.tree),
+            ExprTree::Slice(a, from, to) => {
+                let mut temp = find_refs(&a.tree);
+                for b in [from, to] {
+                    if let Some(b) = b {
+                        temp.append(&mut find_refs(&b.tree));
+                    }
+                }
+                temp
+            }, ExprTree::Match(val, arms) => {
+                let mut temp: Vec<_> = arms.iter().map(|x| find_refs(&x.1.tree)).flatten().collect();
+                temp.append(&mut find_refs(&val.tree));
+                temp
+            }, ExprTree::Let(v, body) => {
+                let mut temp: Vec<_> = v.iter().map(|x| find_refs(&x.1.tree)).flatten().collect();
+                temp.append(&mut find_refs(&body.tree));
+                temp
+            },
             _ => vec![]
     }
 }
@@ -57,9 +180,17 @@ pub fn find_refs(expr: &ExprTree) -> Vec<(u64, u64)> {
 pub fn compute(expr: &ExprTree, db: &tree::DBState, params: &HashMap<String, tree::ExprTree>) -> Result<ExprTree, String> {
     match expr {
         ExprTree::BoolLit(_) | ExprTree::CharLit(_) | ExprTree::DoubleLit(_) |
-        ExprTree::IntLit(_)| ExprTree::NothingLit(_)| ExprTree::LambdaExpr(_) | ExprTree::Ref(_, _, _, _, _) => Ok(expr.clone()),
+        ExprTree::IntLit(_)| ExprTree::NothingLit(_)| ExprTree::Closure(_, _) | ExprTree::Ref(_, _, _, _, _) |
+        ExprTree::StdFunc(_) => Ok(expr.clone()),
+        ExprTree::LambdaExpr(lm) => {
+            let mut env: Vec<_> = params.iter().map(|(n, v)| (n.clone(), Box::new(tree::Node::simple(v.clone())))).collect();
+            env.sort_by(|x, y| x.0.cmp(&y.0));
+            Ok(ExprTree::Closure(lm.clone(), env))
+        },
         ExprTree::JustLit(v) => Ok(ExprTree::JustLit(Box::new(
             tree::Node::simple(compute(&v.tree, db, params)?)))),
+        ExprTree::SumVal(t, i, v) => Ok(ExprTree::SumVal(t.clone(), *i, Box::new(
+            tree::Node::simple(compute(&v.tree, db, params)?)))),
         ExprTree::ArrayLit(v, t) => {
             let mut res_vec = vec![];
             for i in v {
@@ -72,11 +203,99 @@ pub fn compute(expr: &ExprTree, db: &tree::DBState, params: &HashMap<String, tre
                 res_vec.push(Box::new(tree::Node::simple(compute(&i.tree, db, params)?)));
             }
             Ok(ExprTree::TupleLit(res_vec))
+        }, ExprTree::RecordLit(v) => {
+            let mut res_vec = vec![];
+            for (n, i) in v {
+                res_vec.push((n.clone(), Box::new(tree::Node::simple(compute(&i.tree, db, params)?))));
+            }
+            Ok(ExprTree::RecordLit(res_vec))
         }, ExprTree::IfExpr(cond, e1, e2 ) => {
             match compute(&cond.tree, db, params)? {
                 ExprTree::BoolLit(true) => return compute(&e1.tree, db, params),
                 _ => return compute(&e2.tree, db, params)
             }
+        }, ExprTree::And(e1, e2) => {
+            match compute(&e1.tree, db, params)? {
+                ExprTree::BoolLit(true) => compute(&e2.tree, db, params),
+                ExprTree::BoolLit(false) => Ok(ExprTree::BoolLit(false)),
+                _ => Err("Type mismatch in &&".to_string())
+            }
+        }, ExprTree::Or(e1, e2) => {
+            match compute(&e1.tree, db, params)? {
+                ExprTree::BoolLit(true) => Ok(ExprTree::BoolLit(true)),
+                ExprTree::BoolLit(false) => compute(&e2.tree, db, params),
+                _ => Err("Type mismatch in ||".to_string())
+            }
+        }, ExprTree::Not(e) => {
+            match compute(&e.tree, db, params)? {
+                ExprTree::BoolLit(b) => Ok(ExprTree::BoolLit(!b)),
+                _ => Err("Type mismatch in !".to_string())
+            }
+        }, ExprTree::Neg(e) => {
+            match compute(&e.tree, db, params)? {
+                ExprTree::IntLit(v) => Ok(ExprTree::IntLit(-v)),
+                ExprTree::DoubleLit(v) => Ok(ExprTree::DoubleLit(-v)),
+                _ => Err("Type mismatch in unary -".to_string())
+            }
+        }, ExprTree::Match(val, arms) => {
+            let v = compute(&val.tree, db, params)?;
+            for (pat, body) in arms {
+                let mut new_params = params.clone();
+                let matched = match (pat, &v) {
+                    (tree::Pattern::Just(x), ExprTree::JustLit(inner)) => { new_params.insert(x.clone(), inner.tree.clone()); true },
+                    (tree::Pattern::Nothing, ExprTree::NothingLit(_)) | (tree::Pattern::Wildcard, _) => true,
+                    (tree::Pattern::Variant(i, x), ExprTree::SumVal(_, j, inner)) if i == j => {
+                        new_params.insert(x.clone(), inner.tree.clone());
+                        true
+                    },
+                    (tree::Pattern::Typed(x, t), ExprTree::SumVal(tree::Type::Sum(variants), i, inner)) => {
+                        if variants.get(*i as usize).map_or(false, |vt| **vt == *t) {
+                            new_params.insert(x.clone(), inner.tree.clone());
+                            true
+                        } else {false}
+                    },
+                    (tree::Pattern::Typed(x, t), _) if value_has_type(&v, t, db) => { new_params.insert(x.clone(), v.clone()); true },
+                    _ => false
+                };
+                if matched {
+                    return compute(&body.tree, db, &new_params);
+                }
+            }
+            Err("No arm of match applies to the value".to_string())
+        }, ExprTree::Index(arr, i) => {
+            match (compute(&arr.tree, db, params)?, compute(&i.tree, db, params)?) {
+                (ExprTree::ArrayLit(v, t), ExprTree::IntLit(i)) => {
+                    if i >= 0 && (i as usize) < v.len() {
+                        return Ok(ExprTree::JustLit(v[i as usize].clone()));
+                    }
+                    Ok(ExprTree::NothingLit(elem_type(&v, &t, db)?))
+                }, _ => Err("Type mismatch in indexing".to_string())
+            }
+        }, ExprTree::Slice(arr, from, to) => {
+            match compute(&arr.tree, db, params)? {
+                ExprTree::ArrayLit(v, t) => {
+                    let mut bounds = [0, v.len() as i64];
+                    for (k, b) in [from, to].iter().enumerate() {
+                        if let Some(b) = b {
+                            match compute(&b.tree, db, params)? {
+                                ExprTree::IntLit(x) => bounds[k] = x.clamp(0, v.len() as i64),
+                                _ => return Err("Type mismatch in slice".to_string())
+                            }
+                        }
+                    }
+                    if bounds[0] >= bounds[1] {
+                        return Ok(ExprTree::ArrayLit(vec![], Some(elem_type(&v, &t, db)?)));
+                    }
+                    Ok(ExprTree::ArrayLit(v[bounds[0] as usize..bounds[1] as usize].to_vec(), t))
+                }, _ => Err("Type mismatch in slice".to_string())
+            }
+        }, ExprTree::Let(bindings, body) => {
+            let mut new_params = params.clone();
+            for (name, val) in bindings {
+                let res = compute(&val.tree, db, &new_params)?;
+                new_params.insert(name.clone(), res);
+            }
+            compute(&body.tree, db, &new_params)
         }, ExprTree::Mod(e1, e2) => {
             let (r1, r2) = (compute(&e1.tree, db, params)?, compute(&e2.tree, db, params)?);
             if let (ExprTree::IntLit(val1), ExprTree::IntLit(val2)) = (r1, r2) {
@@ -144,6 +363,31 @@ pub fn compute(expr: &ExprTree, db: &tree::DBState, params: &HashMap<String, tre
                         ExprTree::IntLit(v2) => return Ok(ExprTree::BoolLit(comp(greater, nons, &v, &(v2 as f64)))),
                         _ => return Err("Type mismatch in cmp".to_string())
                     }
+                }, ExprTree::BoolLit(v) => {
+                    match r2 {
+                        ExprTree::BoolLit(v2) => return Ok(ExprTree::BoolLit(comp(greater, nons, &v, &v2))),
+                        _ => return Err("Type mismatch in cmp".to_string())
+                    }
+                }, ExprTree::ArrayLit(v, _) => {
+                    match r2 {
+                        ExprTree::ArrayLit(v2, _) => return Ok(ExprTree::BoolLit(comp(greater, nons, &seq_cmp(&v, &v2, db, params)?, &Ordering::Equal))),
+                        _ => return Err("Type mismatch in cmp".to_string())
+                    }
+                }, ExprTree::TupleLit(v) => {
+                    match r2 {
+                        ExprTree::TupleLit(v2) => return Ok(ExprTree::BoolLit(comp(greater, nons, &seq_cmp(&v, &v2, db, params)?, &Ordering::Equal))),
+                        _ => return Err("Type mismatch in cmp".to_string())
+                    }
+                }, ExprTree::JustLit(_) | ExprTree::NothingLit(_) => {
+                    // Nothing is less than any Just.
+                    let ord = match (&r1, &r2) {
+                        (ExprTree::JustLit(_), ExprTree::NothingLit(_)) => Ordering::Greater,
+                        (ExprTree::NothingLit(_), ExprTree::JustLit(_)) => Ordering::Less,
+                        (ExprTree::NothingLit(_), ExprTree::NothingLit(_)) => Ordering::Equal,
+                        (ExprTree::JustLit(v), ExprTree::JustLit(v2)) => seq_cmp(&vec![v.clone()], &vec![v2.clone()], db, params)?,
+                        _ => return Err("Type mismatch in cmp".to_string())
+                    };
+                    return Ok(ExprTree::BoolLit(comp(greater, nons, &ord, &Ordering::Equal)));
                 }, _ => return Err("Type mismatch in cmp".to_string())
             }
         }, ExprTree::Eq(eq, e1, e2) => {
@@ -179,6 +423,24 @@ pub fn compute(expr: &ExprTree, db: &tree::DBState, params: &HashMap<String, tre
                         ExprTree::CharLit(b2) => return Ok(ExprTree::BoolLit(if *eq {b1 == b2} else {b1 != b2})),
                         _ => return Err("Type mismatch".to_string())
                     }
+                }, ExprTree::DoubleLit(b1) => {
+                    // Unlike '<' and '>', equality is reflexive on NaN, so that values containing it can be found again.
+                    match r2 {
+                        ExprTree::DoubleLit(b2) => {
+                            let same = b1 == b2 || b1.is_nan() && b2.is_nan();
+                            return Ok(ExprTree::BoolLit(if *eq {same} else {!same}));
+                        },
+                        _ => return Err("Type mismatch".to_string())
+                    }
+                }, ExprTree::Ref(_, _, _, ent, pos) => {
+                    // Objects are equal when they refer to the same row.
+                    match r2 {
+                        ExprTree::Ref(_, _, _, ent2, pos2) => {
+                            let same = ent == ent2 && pos == pos2;
+                            return Ok(ExprTree::BoolLit(if *eq {same} else {!same}));
+                        },
+                        _ => return Err("Type mismatch".to_string())
+                    }
                 }, ExprTree::TupleLit(vals) => {
                     match r2 {
                         ExprTree::TupleLit(vals2) => {
@@ -194,10 +456,34 @@ pub fn compute(expr: &ExprTree, db: &tree::DBState, params: &HashMap<String, tre
                         },
                         _ => return Err("Type mismatch".to_string())
                     }
+                }, ExprTree::RecordLit(vals) => {
+                    match r2 {
+                        ExprTree::RecordLit(vals2) => {
+                            let mut ok = true;
+                            if vals.len() != vals2.len() { return Err("Type mismatch".to_string()); }
+                            for i in 0..vals.len() {
+                                if vals[i].0 != vals2[i].0 { return Err("Type mismatch".to_string()); }
+                                match compute(&ExprTree::Eq(true, vals[i].1.clone(), vals2[i].1.clone()), db, params)? {
+                                    ExprTree::BoolLit(true) => {},
+                                    _ => {ok = false; break;}
+                                }
+                            }
+                            return Ok(ExprTree::BoolLit(if *eq {ok} else {!ok}));
+                        },
+                        _ => return Err("Type mismatch".to_string())
+                    }
                 }, ExprTree::NothingLit(_) => {
                     return Ok(ExprTree::BoolLit(match r2 {
                         ExprTree::NothingLit(_) => *eq, _ => !*eq
                     }))
+                }, ExprTree::SumVal(_, i, v) => {
+                    match r2 {
+                        ExprTree::SumVal(_, i2, v2) => {
+                            if i != i2 { return Ok(ExprTree::BoolLit(!*eq)); }
+                            return compute(&ExprTree::Eq(*eq, v, v2), db, params);
+                        },
+                        _ => return Err("Type mismatch".to_string())
+                    }
                 }, ExprTree::JustLit(v) => {
                     match r2 {
                         ExprTree::JustLit(v2) => return Ok(ExprTree::BoolLit(
@@ -211,58 +497,161 @@ pub fn compute(expr: &ExprTree, db: &tree::DBState, params: &HashMap<String, tre
         }, ExprTree::Ident(name) => {
             match params.get(name) {
                 Some(e) => Ok(e.clone()),
-                None => return Err(format!["Undefined identifier '{}'", name])
+                None => match db.func(name) {
+                    Some(lm) => Ok(ExprTree::LambdaExpr(Box::new(lm.clone()))),
+                    None => return Err(format!["Undefined identifier '{}'", name])
+                }
+            }
+        }, ExprTree::For(ents, lm, clauses) => {
+            let mut rows = vec![];
+            for e in ents {
+                rows.push((db.header.iter().position(|(x, _)| x == e).ok_or(format!["Unrecognized entity '{}' in 'for'", e])?, 0));
             }
-        }, ExprTree::For(ent, lm) => {
-            let pos = db.header.iter().position(|(x, _)| *x == *ent).unwrap();
+            let counts: Vec<_> = rows.iter().map(|x| db.row_count(x.0) as u64).collect();
+            // Results with their sort keys, which are only computed for the rows giving a result.
             let mut res = vec![];
-            let iter_len = match db.data.get(&(pos as u64, 0)) {
-                Some(a) => a.len(),
-                None => return Ok(ExprTree::ArrayLit(vec![], None))
-            };
-            for i in 0..iter_len {
-                let mut new_params = params.clone();
-                new_params.insert(lm.params[0].0.clone(), ExprTree::Ref(String::new(), String::new(), Box::new(
-                    tree::Node::simple(ExprTree::TupleLit(vec![]))), pos as u64, i as u64));
-                match compute(&lm.code.tree, db, &new_params)? {
-                    ExprTree::JustLit(t) => res.push(t),
+            let mut more = counts.iter().all(|x| *x > 0);
+            while more {
+                match row_apply(lm, &rows, db, params)? {
+                    ExprTree::JustLit(t) => res.push((match &clauses.order {
+                        Some((key, _)) => Box::new(tree::Node::simple(row_apply(key, &rows, db, params)?)),
+                        None => Box::new(tree::Node::simple(ExprTree::TupleLit(vec![])))
+                    }, t)),
                     _ => {}
                 }
+                more = next_rows(&mut rows, &counts);
             }
-            Ok(ExprTree::ArrayLit(res, None))
-        }, ExprTree::Call(e1, e2) => {
-            match &e1.tree {
-                ExprTree::LambdaExpr(ref lm) => {
-                    let mut new_params = params.clone();
-                    for i in 0..lm.params.len() {
-                        new_params.insert(lm.params[i].0.clone(), compute(&e2[i].tree, db, params)?);
+            if let Some((_, desc)) = &clauses.order {
+                let mut err = None;
+                // The sort is stable, so rows with equal keys stay in storage order.
+                res.sort_by(|x, y| match seq_cmp(&vec![x.0.clone()], &vec![y.0.clone()], db, params) {
+                    Ok(o) => if *desc {o.reverse()} else {o},
+                    Err(e) => {
+                        err = Some(e);
+                        Ordering::Equal
                     }
-                    if let Some((n, _)) = &lm.named {
-                        new_params.insert(n.clone(), ExprTree::LambdaExpr(Box::new(*lm.clone())));
+                });
+                if let Some(e) = err {
+                    return Err(e);
+                }
+            }
+            let mut bounds = vec![];
+            for val in [&clauses.offset, &clauses.limit] {
+                bounds.push(match val {
+                    Some(v) => match compute(&v.tree, db, params)? {
+                        ExprTree::IntLit(n) if n >= 0 => Some(n as usize),
+                        ExprTree::IntLit(_) => return Err("Negative limit or offset in 'for'".to_string()),
+                        _ => return Err("Type mismatch in 'for'".to_string())
+                    },
+                    None => None
+                });
+            }
+            let res = res.into_iter().map(|x| x.1).skip(bounds[0].unwrap_or(0));
+            let t = match &lm.ret {
+                Some(tree::Type::Maybe(t)) => Some(*t.clone()),
+                _ => None
+            };
+            Ok(ExprTree::ArrayLit(match bounds[1] {
+                Some(n) => res.take(n).collect(),
+                None => res.collect()
+            }, t))
+        }, ExprTree::Group(ent, key, aggs) => {
+            let pos = db.header.iter().position(|(x, _)| *x == *ent).ok_or(format!["Unrecognized entity '{}' in 'group'", ent])?;
+            // Groups in the order of their first row: key, row count and an accumulator for each aggregate.
+            let mut groups: Vec<(ExprTree, i64, Vec<Option<ExprTree>>)> = vec![];
+            for i in 0..db.row_count(pos) {
+                let k = row_apply(key, &[(pos, i as u64)], db, params)?;
+                let mut found = None;
+                for (g, x) in groups.iter().enumerate() {
+                    let eq = ExprTree::Eq(true, Box::new(tree::Node::simple(x.0.clone())), Box::new(tree::Node::simple(k.clone())));
+                    if let ExprTree::BoolLit(true) = compute(&eq, db, params)? {
+                        found = Some(g);
+                        break;
                     }
-                    compute(&lm.code.tree, db, &new_params)
-                }, ExprTree::Ident(n) => {
-                    match params.get(n) {
-                        Some(ExprTree::LambdaExpr(lm)) => {
-                            let mut new_params = params.clone();
-                            for i in 0..lm.params.len() {
-                                new_params.insert(lm.params[i].0.clone(), compute(&e2[i].tree, db, params)?);
-                            }
-                            compute(&lm.code.tree, db, &new_params)
-                        }, _ => return Err("Unable to call not a function".to_string())
+                }
+                let g = match found {
+                    Some(g) => g,
+                    None => {
+                        groups.push((k, 0, vec![None; aggs.len()]));
+                        groups.len() - 1
+                    }
+                };
+                groups[g].1 += 1;
+                for (j, (_, agg, arg)) in aggs.iter().enumerate() {
+                    if let Some(lm) = arg {
+                        let v = row_apply(lm, &[(pos, i as u64)], db, params)?;
+                        groups[g].2[j] = Some(match groups[g].2[j].take() {
+                            Some(acc) => aggregate_step(agg, acc, v, db, params)?,
+                            None => v
+                        });
                     }
-                }, _ => return Err("Type mismatch in call".to_string())
+                }
+            }
+            let mut res = vec![];
+            for (k, count, accs) in groups {
+                let mut fields = vec![("key".to_string(), Box::new(tree::Node::simple(k)))];
+                for ((name, agg, _), acc) in aggs.iter().zip(accs.into_iter()) {
+                    let val = match (agg.as_str(), acc) {
+                        ("avg", Some(ExprTree::IntLit(s))) => ExprTree::DoubleLit(s as f64 / count as f64),
+                        ("avg", Some(ExprTree::DoubleLit(s))) => ExprTree::DoubleLit(s / count as f64),
+                        (_, Some(v)) => v,
+                        (_, None) => ExprTree::IntLit(count)
+                    };
+                    fields.push((name.clone(), Box::new(tree::Node::simple(val))));
+                }
+                fields.sort_by(|x, y| x.0.cmp(&y.0));
+                res.push(Box::new(tree::Node::simple(ExprTree::RecordLit(fields))));
+            }
+            // The record type follows from the result types the checker recorded on the lambdas.
+            let mut field_types = vec![("key".to_string(), key.ret.clone().map(Box::new))];
+            for (name, agg, arg) in aggs {
+                field_types.push((name.clone(), match (agg.as_str(), arg) {
+                    ("avg", _) => Some(Box::new(tree::Type::Double)),
+                    (_, Some(lm)) => lm.ret.clone().map(Box::new),
+                    (_, None) => Some(Box::new(tree::Type::Int))
+                }));
+            }
+            field_types.sort_by(|x, y| x.0.cmp(&y.0));
+            let t = field_types.into_iter().map(|(n, t)| t.map(|t| (n, t))).collect::<Option<Vec<_>>>();
+            Ok(ExprTree::ArrayLit(res, t.map(tree::Type::Record)))
+        }, ExprTree::Call(e1, e2) => {
+            if let ExprTree::Dot(d1, d2) = &e1.tree {
+                if let (ExprTree::Ident(s1), ExprTree::Ident(s2)) = (&d1.tree, &d2.tree) {
+                    if is_std(s1, db, params) {
+                        let mut args = vec![];
+                        for x in e2 {
+                            args.push(compute(&x.tree, db, params)?);
+                        }
+                        return stdlib::call(s2, args, db, params);
+                    }
+                }
+            }
+            let f = compute(&e1.tree, db, params)?;
+            let mut args = vec![];
+            for x in e2 {
+                args.push(compute(&x.tree, db, params)?);
             }
+            apply(&f, args, db, params)
         }, ExprTree::Dot(e1, e2) => {
+            if let ExprTree::IntLit(i) = e2.tree {
+                return match compute(&e1.tree, db, params)? {
+                    ExprTree::TupleLit(v) if i >= 0 && (i as usize) < v.len() => Ok(v[i as usize].tree.clone()),
+                    _ => Err(format!["Incorrect tuple projection"])
+                };
+            }
             if let (ExprTree::Ident(s1), ExprTree::Ident(s2)) = (e1.tree.clone(), e2.tree.clone()) {
-                // if s1 == "Std" {
-                //     // Implementation of std functions.
-                //     return Err(format!["({}, {}): standard function '{}' not found.", s1, e1.ln, e1.col]);
-                // } else 
-                if let Some((_, ent)) = db.header.iter().find(|(x, _)| *x == *s1) {
-                    if let Some(a) = ent.iter().find(|x| x.name == *s2) {
-                        if let tree::AttrFlag::Global = a.flag {
-                            return Err(format!["Globals not supported yet."]);
+                if is_std(&s1, db, params) {
+                    if stdlib::exists(&s2) {
+                        return Ok(ExprTree::StdFunc(s2));
+                    }
+                    return Err(format!["Standard function '{}' not found", s2]);
+                } else if let Some(ent) = db.header.iter().position(|(x, _)| *x == *s1) {
+                    if let Some(a) = db.header[ent].1.iter().position(|x| x.name == *s2) {
+                        if let tree::AttrFlag::Global = db.header[ent].1[a].flag {
+                            return match db.globals.get(&(ent as u64, a as u64)) {
+                                Some(v) => Ok(v.tree.clone()),
+                                None => Err(format!["Global attribute '{}' of entity '{}' is not set", s2, s1])
+                            };
                         }
                     }
                 }
@@ -271,9 +660,20 @@ pub fn compute(expr: &ExprTree, db: &tree::DBState, params: &HashMap<String, tre
                 match compute(&t, db, params)? {
                     ExprTree::Ref(_, _, _, ent, pos) => {
                         if let Some(attr) = db.header[ent as usize].1.iter().position(|x| x.name == *s) {
+                            if let tree::AttrFlag::Computable = db.header[ent as usize].1[attr].flag {
+                                let lm = db.header[ent as usize].1[attr].default.clone().ok_or("Computable attribute without a defining lambda")?;
+                                return compute(&ExprTree::Call(Box::new(tree::Node::simple(ExprTree::LambdaExpr(Box::new(lm)))),
+                                    vec![Box::new(tree::Node::simple(ExprTree::Ref(String::new(), String::new(),
+                                        Box::new(tree::Node::simple(ExprTree::TupleLit(vec![]))), ent, pos)))]), db, &HashMap::new());
+                            }
                             return Ok(db.data[&(ent, attr as u64)][pos as usize].tree.clone());
                         }
                         return Err(format!["Incorrect reference in dot opeartor"])
+                    }, ExprTree::RecordLit(v) => {
+                        return match v.into_iter().find(|(n, _)| *n == s) {
+                            Some((_, x)) => Ok(x.tree),
+                            None => Err(format!["Record has no field '{}'", s])
+                        };
                     }, _ => return Err(format!["Incorrect application of dot opeartor"])
                 }
             }
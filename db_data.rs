@@ -1,5 +1,5 @@
 use std::{collections::HashMap, fs};
-use crate::tree::{Attr, AttrFlag, CoordStr, DBState, ExprTree, Lambda, Node, SpType, Type};
+use crate::tree::{Attr, AttrFlag, CoordStr, DBState, ExprTree, ForClauses, Lambda, Node, Pattern, SpType, Type};
 
 fn binary_read_string(b: &[u8], index: &mut usize) -> Option<String> {
     let mut i = *index;
@@ -110,27 +110,199 @@ fn binary_read_expr_tree(b: &[u8], index: &mut usize) -> Option<ExprTree> {
         }, 23 => {
             let ent = binary_read_string(b, index)?;
             let lm = binary_parse_lambda(b, index)?;
-            Some(ExprTree::For(ent, Box::new(lm)))
+            Some(ExprTree::For(vec![ent], Box::new(lm), Box::new(ForClauses::new())))
+        }, 24 | 25 => {
+            let op = *b.get(*index - 1)?;
+            let (p1, p2) = (Box::new(binary_read_expr(b, index)?)
+                , Box::new(binary_read_expr(b, index)?));
+            Some(if op == 24 {ExprTree::And(p1, p2)} else {ExprTree::Or(p1, p2)})
+        }, 26 => {
+            Some(ExprTree::Not(Box::new(binary_read_expr(b, index)?)))
+        }, 27 => {
+            Some(ExprTree::Neg(Box::new(binary_read_expr(b, index)?)))
+        }, 28 => {
+            let mut bindings = vec![];
+            while *b.get(*index)? != 0 {
+                let name = binary_read_string(b, index)?;
+                bindings.push((name, Box::new(binary_read_expr(b, index)?)));
+            }
+            *index += 1;
+            Some(ExprTree::Let(bindings, Box::new(binary_read_expr(b, index)?)))
+        }, 29 => {
+            let val = binary_read_expr(b, index)?;
+            let mut arms = vec![];
+            while *b.get(*index)? != 0 {
+                *index += 1;
+                let pat = match *b.get(*index - 1)? {
+                    1 => Pattern::Just(binary_read_string(b, index)?),
+                    2 => Pattern::Nothing,
+                    3 => {
+                        let name = binary_read_string(b, index)?;
+                        match binary_read_type(b, index)? {
+                            SpType::Reg(t) => Pattern::Typed(name, t),
+                            _ => return None
+                        }
+                    },
+                    4 => Pattern::Wildcard,
+                    5 => {
+                        let i = u64::from_le_bytes(b.get(*index..*index + 8)?.try_into().ok()?);
+                        *index += 8;
+                        Pattern::Variant(i, binary_read_string(b, index)?)
+                    },
+                    _ => return None
+                };
+                arms.push((pat, Box::new(binary_read_expr(b, index)?)));
+            }
+            *index += 1;
+            Some(ExprTree::Match(Box::new(val), arms))
+        }, 30 => {
+            let t = match binary_read_type(b, index)? {
+                SpType::Reg(t) => t,
+                _ => return None
+            };
+            let i = u64::from_le_bytes(b.get(*index..*index + 8)?.try_into().ok()?);
+            *index += 8;
+            Some(ExprTree::SumVal(t, i, Box::new(binary_read_expr(b, index)?)))
+        }, 31 => {
+            Some(ExprTree::StdFunc(binary_read_string(b, index)?))
+        }, 32 => {
+            let (p1, p2) = (Box::new(binary_read_expr(b, index)?)
+                , Box::new(binary_read_expr(b, index)?));
+            Some(ExprTree::Index(p1, p2))
+        }, 33 => {
+            let arr = Box::new(binary_read_expr(b, index)?);
+            let mut bounds = vec![];
+            for _ in 0..2 {
+                *index += 1;
+                bounds.push(if *b.get(*index - 1)? == 1 {Some(Box::new(binary_read_expr(b, index)?))} else {None});
+            }
+            let to = bounds.pop()?;
+            Some(ExprTree::Slice(arr, bounds.pop()?, to))
+        }, 34 => {
+            let mut fields = vec![];
+            while *b.get(*index)? != 0 {
+                let name = binary_read_string(b, index)?;
+                fields.push((name, Box::new(binary_read_expr(b, index)?)));
+            }
+            *index += 1;
+            Some(ExprTree::RecordLit(fields))
+        }, 35 => {
+            let lm = binary_parse_lambda(b, index)?;
+            let mut env = vec![];
+            while *b.get(*index)? != 0 {
+                let name = binary_read_string(b, index)?;
+                env.push((name, Box::new(binary_read_expr(b, index)?)));
+            }
+            *index += 1;
+            Some(ExprTree::Closure(Box::new(lm), env))
+        }, 36 => {
+            let ent = binary_read_string(b, index)?;
+            let key = binary_parse_lambda(b, index)?;
+            let mut aggs = vec![];
+            while *b.get(*index)? != 0 {
+                let name = binary_read_string(b, index)?;
+                let agg = binary_read_string(b, index)?;
+                *index += 1;
+                let arg = if *b.get(*index - 1)? == 1 {Some(Box::new(binary_parse_lambda(b, index)?))} else {None};
+                aggs.push((name, agg, arg));
+            }
+            *index += 1;
+            Some(ExprTree::Group(ent, Box::new(key), aggs))
+        }, 37 => {
+            let ent = binary_read_string(b, index)?;
+            let lm = binary_parse_lambda(b, index)?;
+            Some(ExprTree::For(vec![ent], Box::new(lm), Box::new(binary_read_for_clauses(b, index)?)))
+        }, 38 => {
+            let mut ents = vec![];
+            while *b.get(*index)? != 0 {
+                ents.push(binary_read_string(b, index)?);
+            }
+            *index += 1;
+            let lm = binary_parse_lambda(b, index)?;
+            Some(ExprTree::For(ents, Box::new(lm), Box::new(binary_read_for_clauses(b, index)?)))
         }
         _ => None
     }
 }
+fn binary_read_for_clauses(b: &[u8], index: &mut usize) -> Option<ForClauses> {
+    let mut clauses = ForClauses::new();
+    *index += 1;
+    let order = *b.get(*index - 1)?;
+    if order != 0 {
+        clauses.order = Some((binary_parse_lambda(b, index)?, order == 2));
+    }
+    for val in [&mut clauses.limit, &mut clauses.offset] {
+        *index += 1;
+        if *b.get(*index - 1)? == 1 {
+            *val = Some(binary_read_expr(b, index)?);
+        }
+    }
+    Some(clauses)
+}
+fn binary_write_for_clauses(clauses: &ForClauses) -> Result<Vec<u8>, String> {
+    let mut res = vec![];
+    match &clauses.order {
+        Some((key, desc)) => {
+            res.push(if *desc {2} else {1});
+            res.append(&mut binary_write_lambda(key)?);
+        }, None => res.push(0)
+    }
+    for val in [&clauses.limit, &clauses.offset] {
+        match val {
+            Some(v) => {
+                res.push(1);
+                res.append(&mut binary_write_expr(&v.tree)?);
+            }, None => res.push(0)
+        }
+    }
+    Ok(res)
+}
 fn binary_read_expr(b: &[u8], index: &mut usize) -> Option<Node> {
     Some(Node::simple(binary_read_expr_tree(b, index)?))
 }
-fn write_trees(init: Vec<u8>, trees: Vec<&Node>) -> Vec<u8> {
+fn write_trees(init: Vec<u8>, trees: Vec<&Node>) -> Result<Vec<u8>, String> {
     let mut res = init;
     for t in trees {
-        res.append(&mut binary_write_expr(&t.tree));
+        res.append(&mut binary_write_expr(&t.tree)?);
     }
-    res
+    Ok(res)
 }
-fn binary_write_expr(t: &ExprTree) -> Vec<u8> {
-    match t {
-        ExprTree::For(ent, lm) => {
-            let mut res = vec![23];
+fn binary_write_expr(t: &ExprTree) -> Result<Vec<u8>, String> {
+    Ok(match t {
+        ExprTree::For(ents, lm, clauses) => {
+            // Single entity comprehensions without clauses keep the older encoding.
+            if ents.len() != 1 {
+                let mut res = vec![38];
+                for ent in ents {
+                    res.append(&mut binary_write_string(ent));
+                }
+                res.push(0);
+                res.append(&mut binary_write_lambda(lm)?);
+                res.append(&mut binary_write_for_clauses(clauses)?);
+                return Ok(res);
+            }
+            let mut res = vec![if clauses.is_empty() {23} else {37}];
+            res.append(&mut binary_write_string(&ents[0]));
+            res.append(&mut binary_write_lambda(lm)?);
+            if !clauses.is_empty() {
+                res.append(&mut binary_write_for_clauses(clauses)?);
+            }
+            res},
+        ExprTree::Group(ent, key, aggs) => {
+            let mut res = vec![36];
             res.append(&mut binary_write_string(ent));
-            res.append(&mut binary_write_lambda(lm));
+            res.append(&mut binary_write_lambda(key)?);
+            for (name, agg, arg) in aggs {
+                res.append(&mut binary_write_string(name));
+                res.append(&mut binary_write_string(agg));
+                match arg {
+                    Some(lm) => {
+                        res.push(1);
+                        res.append(&mut binary_write_lambda(lm)?);
+                    }, None => res.push(0)
+                }
+            }
+            res.push(0);
             res},
         ExprTree::IntLit(v) => {
             let mut res = vec![1];
@@ -148,53 +320,131 @@ fn binary_write_expr(t: &ExprTree) -> Vec<u8> {
             res},
         ExprTree::ArrayLit(v, opt_type) => {
             let mut res = vec![5];
-            res.append(&mut v.iter().map(|x| binary_write_expr(&x.as_ref().tree)).flatten().collect());
+            res.append(&mut v.iter().map(|x| binary_write_expr(&x.as_ref().tree)).collect::<Result<Vec<_>, _>>()?.concat());
             res.push(0);
-            if res.len() == 1 {
-                res.append(&mut binary_write_type(&SpType::Reg(opt_type.clone().expect("empty array literal with no type"))));
+            if v.len() == 0 {
+                let t = opt_type.clone().ok_or("unable to write an empty array without an element type.")?;
+                res.append(&mut binary_write_type(&SpType::Reg(t))?);
             }
             res},
         ExprTree::TupleLit(v) => {
             let mut res = vec![8];
-            res.append(&mut v.iter().map(|x| binary_write_expr(&x.as_ref().tree)).flatten().collect());
+            res.append(&mut v.iter().map(|x| binary_write_expr(&x.as_ref().tree)).collect::<Result<Vec<_>, _>>()?.concat());
             res.push(0);
             res},
         ExprTree::JustLit(v) => {
             let mut res = vec![6];
-            res.append(&mut binary_write_expr(&v.tree));
+            res.append(&mut binary_write_expr(&v.tree)?);
             res},
         ExprTree::NothingLit(t) => {
-            [vec![7], binary_write_type(&SpType::Reg(t.clone()))].concat()
+            [vec![7], binary_write_type(&SpType::Reg(t.clone()))?].concat()
         }
         ExprTree::Ident(s) => {
             let mut res = vec![9];
             res.append(&mut binary_write_string(s));
             res},
-        ExprTree::Plus(c1, c2) => {write_trees(vec![10], vec![c1, c2])},
-        ExprTree::Minus(c1, c2) => {write_trees(vec![11], vec![c1, c2])},
-        ExprTree::Mul(c1, c2) => {write_trees(vec![12], vec![c1, c2])},
-        ExprTree::Div(c1, c2) => {write_trees(vec![13], vec![c1, c2])},
-        ExprTree::Mod(c1, c2) => {write_trees(vec![14], vec![c1, c2])},
-        ExprTree::Exp(c1, c2) => {write_trees(vec![15], vec![c1, c2])},
-        ExprTree::Dot(c1, c2) => {write_trees(vec![16], vec![c1, c2])},
+        ExprTree::Plus(c1, c2) => {write_trees(vec![10], vec![c1, c2])?},
+        ExprTree::Minus(c1, c2) => {write_trees(vec![11], vec![c1, c2])?},
+        ExprTree::Mul(c1, c2) => {write_trees(vec![12], vec![c1, c2])?},
+        ExprTree::Div(c1, c2) => {write_trees(vec![13], vec![c1, c2])?},
+        ExprTree::Mod(c1, c2) => {write_trees(vec![14], vec![c1, c2])?},
+        ExprTree::Exp(c1, c2) => {write_trees(vec![15], vec![c1, c2])?},
+        ExprTree::Dot(c1, c2) => {write_trees(vec![16], vec![c1, c2])?},
         ExprTree::Call(f, args) => {
             let mut res = vec![17];
-            res.append(&mut binary_write_expr(&f.tree));
-            res.append(&mut args.iter().map(|x| binary_write_expr(&x.as_ref().tree)).flatten().collect());
+            res.append(&mut binary_write_expr(&f.tree)?);
+            res.append(&mut args.iter().map(|x| binary_write_expr(&x.as_ref().tree)).collect::<Result<Vec<_>, _>>()?.concat());
             res.push(0);
             res},
         ExprTree::Eq(b, c1, c2) => {
-            write_trees(vec![18, if *b {1} else {0}], vec![c1, c2])},
+            write_trees(vec![18, if *b {1} else {0}], vec![c1, c2])?},
         ExprTree::Cmp(gr, st, c1, c2) => {
-            write_trees(vec![19, if *gr {1} else {0}, if *st {1} else {0}], vec![c1, c2])},
-        ExprTree::IfExpr(c1, c2, c3) => {write_trees(vec![20], vec![c1, c2, c3])},
+            write_trees(vec![19, if *gr {1} else {0}, if *st {1} else {0}], vec![c1, c2])?},
+        ExprTree::IfExpr(c1, c2, c3) => {write_trees(vec![20], vec![c1, c2, c3])?},
         ExprTree::LambdaExpr(lm) => {
             let mut res = vec![21];
-            res.append(&mut binary_write_lambda(lm));
+            res.append(&mut binary_write_lambda(lm)?);
             res},
         ExprTree::Ref(_, _, _, ent, pos) => {
-            [vec![22], ent.to_le_bytes().to_vec(), pos.to_le_bytes().to_vec()].concat()}
-    }
+            [vec![22], ent.to_le_bytes().to_vec(), pos.to_le_bytes().to_vec()].concat()},
+        ExprTree::And(c1, c2) => {write_trees(vec![24], vec![c1, c2])?},
+        ExprTree::Or(c1, c2) => {write_trees(vec![25], vec![c1, c2])?},
+        ExprTree::Not(c) => {write_trees(vec![26], vec![c])?},
+        ExprTree::Neg(c) => {write_trees(vec![27], vec![c])?},
+        ExprTree::Let(bindings, body) => {
+            let mut res = vec![28];
+            for (name, val) in bindings {
+                res.append(&mut binary_write_string(name));
+                res.append(&mut binary_write_expr(&val.tree)?);
+            }
+            res.push(0);
+            res.append(&mut binary_write_expr(&body.tree)?);
+            res},
+        ExprTree::Match(val, arms) => {
+            let mut res = vec![29];
+            res.append(&mut binary_write_expr(&val.tree)?);
+            for (pat, body) in arms {
+                match pat {
+                    Pattern::Just(x) => {
+                        res.push(1);
+                        res.append(&mut binary_write_string(x));
+                    }, Pattern::Nothing => res.push(2),
+                    Pattern::Typed(x, t) => {
+                        res.push(3);
+                        res.append(&mut binary_write_string(x));
+                        res.append(&mut binary_write_type(&SpType::Reg(t.clone()))?);
+                    }, Pattern::Wildcard => res.push(4),
+                    Pattern::Variant(i, x) => {
+                        res.push(5);
+                        res.append(&mut i.to_le_bytes().to_vec());
+                        res.append(&mut binary_write_string(x));
+                    }
+                }
+                res.append(&mut binary_write_expr(&body.tree)?);
+            }
+            res.push(0);
+            res},
+        ExprTree::SumVal(t, i, v) => {
+            let mut res = vec![30];
+            res.append(&mut binary_write_type(&SpType::Reg(t.clone()))?);
+            res.append(&mut i.to_le_bytes().to_vec());
+            res.append(&mut binary_write_expr(&v.tree)?);
+            res},
+        ExprTree::StdFunc(n) => {
+            let mut res = vec![31];
+            res.append(&mut binary_write_string(n));
+            res},
+        ExprTree::Index(c1, c2) => {write_trees(vec![32], vec![c1, c2])?},
+        ExprTree::Slice(arr, from, to) => {
+            let mut res = vec![33];
+            res.append(&mut binary_write_expr(&arr.tree)?);
+            for bound in [from, to] {
+                match bound {
+                    Some(v) => {
+                        res.push(1);
+                        res.append(&mut binary_write_expr(&v.tree)?);
+                    }, None => res.push(0)
+                }
+            }
+            res},
+        ExprTree::Closure(lm, env) => {
+            let mut res = vec![35];
+            res.append(&mut binary_write_lambda(lm)?);
+            for (n, x) in env {
+                res.append(&mut binary_write_string(n));
+                res.append(&mut binary_write_expr(&x.tree)?);
+            }
+            res.push(0);
+            res},
+        ExprTree::RecordLit(v) => {
+            let mut res = vec![34];
+            for (n, x) in v {
+                res.append(&mut binary_write_string(n));
+                res.append(&mut binary_write_expr(&x.tree)?);
+            }
+            res.push(0);
+            res}
+    })
 }
 
 fn binary_read_type(b: &[u8], index: &mut usize) -> Option<SpType> {
@@ -243,11 +493,36 @@ fn binary_read_type(b: &[u8], index: &mut usize) -> Option<SpType> {
                 *index += 8;
                 Some(SpType::Gen(v, l, counter))
             } else {Some(SpType::Restrict(v, l))}
+        }, 13 => {
+            let mut fields = vec![];
+            while *b.get(*index)? != 0 {
+                let name = binary_read_string(b, index)?;
+                fields.push((name, Box::new(match binary_read_type(b, index)? {
+                    SpType::Reg(t) => t,
+                    _ => return None
+                })));
+            }
+            *index += 1;
+            Some(SpType::Reg(Type::Record(fields)))
+        }, 14 => {
+            Some(SpType::Reg(Type::Alias(CoordStr::new(binary_read_string(b, index)?))))
+        }, 15 => {
+            Some(SpType::Reg(Type::Var(binary_read_string(b, index)?)))
+        }, 16 => {
+            let mut vars = vec![];
+            while *b.get(*index)? != 0 {
+                vars.push(binary_read_string(b, index)?);
+            }
+            *index += 1;
+            match binary_read_type(b, index)? {
+                SpType::Reg(t) => Some(SpType::Reg(Type::Forall(vars, Box::new(t)))),
+                _ => None
+            }
         }, _ => None
     }
 }
-fn binary_write_type(t: &SpType) -> Vec<u8> {
-    match t {
+fn binary_write_type(t: &SpType) -> Result<Vec<u8>, String> {
+    Ok(match t {
         SpType::Reg(Type::Object(s)) => {
             let mut res = vec![1];
             res.append(&mut binary_write_string(&s.name));
@@ -258,39 +533,63 @@ fn binary_write_type(t: &SpType) -> Vec<u8> {
         SpType::Reg(Type::Double) => vec![5],
         SpType::Reg(Type::FuncType(v)) => {
             let mut res = vec![6];
-            res.append(&mut v.iter().map(|x| binary_write_type(&SpType::Reg(*x.clone()))).flatten().collect());
+            res.append(&mut v.iter().map(|x| binary_write_type(&SpType::Reg(*x.clone()))).collect::<Result<Vec<_>, _>>()?.concat());
             res.push(0);
             res},
             SpType::Reg(Type::Tuple(v)) => {
             let mut res = vec![9];
-            res.append(&mut v.iter().map(|x| binary_write_type(&SpType::Reg(*x.clone()))).flatten().collect());
+            res.append(&mut v.iter().map(|x| binary_write_type(&SpType::Reg(*x.clone()))).collect::<Result<Vec<_>, _>>()?.concat());
             res.push(0);
             res},
         SpType::Reg(Type::Sum(v)) => {
             let mut res = vec![10];
-            res.append(&mut v.iter().map(|x| binary_write_type(&SpType::Reg(*x.clone()))).flatten().collect());
+            res.append(&mut v.iter().map(|x| binary_write_type(&SpType::Reg(*x.clone()))).collect::<Result<Vec<_>, _>>()?.concat());
+            res.push(0);
+            res},
+        SpType::Reg(Type::Record(v)) => {
+            let mut res = vec![13];
+            for (n, t) in v {
+                res.append(&mut binary_write_string(n));
+                res.append(&mut binary_write_type(&SpType::Reg(*t.clone()))?);
+            }
             res.push(0);
             res},
+        SpType::Reg(Type::Var(s)) => {
+            let mut res = vec![15];
+            res.append(&mut binary_write_string(s));
+            res},
+        SpType::Reg(Type::Forall(vars, t)) => {
+            let mut res = vec![16];
+            for v in vars {
+                res.append(&mut binary_write_string(v));
+            }
+            res.push(0);
+            res.append(&mut binary_write_type(&SpType::Reg(*t.clone()))?);
+            res},
+        SpType::Reg(Type::Alias(s)) => {
+            let mut res = vec![14];
+            res.append(&mut binary_write_string(&s.name));
+            res},
         SpType::Reg(Type::Array(v0)) => {
             let mut res = vec![7];
-            res.append(&mut binary_write_type(&SpType::Reg(*v0.clone())));
+            res.append(&mut binary_write_type(&SpType::Reg(*v0.clone()))?);
             res},
         SpType::Reg(Type::Maybe(v)) => {
             let mut res = vec![8];
-            res.append(&mut binary_write_type(&SpType::Reg(*v.clone())));
+            res.append(&mut binary_write_type(&SpType::Reg(*v.clone()))?);
             res},
         SpType::Gen(t, l, cnt) => {
             let mut res = vec![11];
-            res.append(&mut binary_write_type(&SpType::Reg(*t.clone())));
-            res.append(&mut binary_write_lambda(l));
+            res.append(&mut binary_write_type(&SpType::Reg(*t.clone()))?);
+            res.append(&mut binary_write_lambda(l)?);
             res.append(&mut cnt.to_le_bytes().to_vec());
             res},
         SpType::Restrict(t, l) =>  {
             let mut res = vec![12];
-            res.append(&mut binary_write_type(&SpType::Reg(*t.clone())));
-            res.append(&mut binary_write_lambda(l));
+            res.append(&mut binary_write_type(&SpType::Reg(*t.clone()))?);
+            res.append(&mut binary_write_lambda(l)?);
             res}
-    }
+    })
 }
 
 fn binary_parse_lambda(b: &[u8], index: &mut usize) -> Option<Lambda> {
@@ -302,6 +601,23 @@ fn binary_parse_lambda(b: &[u8], index: &mut usize) -> Option<Lambda> {
         }));
     }
     *index += 1;
+    let mut tvars = vec![];
+    // Lambdas without type variables are written the same way as before they were supported.
+    if *b.get(*index)? == 2 {
+        *index += 1;
+        while *b.get(*index)? != 0 {
+            tvars.push(binary_read_string(b, index)?);
+        }
+        *index += 1;
+    }
+    let mut ret = None;
+    if *b.get(*index)? == 3 {
+        *index += 1;
+        match binary_read_type(b, index)? {
+            SpType::Reg(t) => ret = Some(t),
+            _ => return None
+        }
+    }
     let mut name = None;
     match b.get(*index) {
         Some(c) if *c == 1 => {
@@ -315,24 +631,36 @@ fn binary_parse_lambda(b: &[u8], index: &mut usize) -> Option<Lambda> {
         _ => {*index += 1;}
     }
     let expr = binary_read_expr(b, index)?;
-    Some(Lambda { params: res_vec, code: expr, named: name })
+    Some(Lambda { params: res_vec, code: expr, named: name, tvars: tvars, ret: ret })
 }
-fn binary_write_lambda(l: &Lambda) -> Vec<u8> {
+fn binary_write_lambda(l: &Lambda) -> Result<Vec<u8>, String> {
     let mut res = vec![];
     for i in &l.params {
         res.append(&mut binary_write_string(&i.0));
-        res.append(&mut binary_write_type(&SpType::Reg(i.1.clone())));
+        res.append(&mut binary_write_type(&SpType::Reg(i.1.clone()))?);
     }
     res.push(0);
+    if l.tvars.len() != 0 {
+        res.push(2);
+        for v in &l.tvars {
+            res.append(&mut binary_write_string(v));
+        }
+        res.push(0);
+    }
+    // Only lambdas that went through the type checker have a result type.
+    if let Some(t) = &l.ret {
+        res.push(3);
+        res.append(&mut binary_write_type(&SpType::Reg(t.clone()))?);
+    }
     match &l.named {
         Some((n, t)) => {
             res.push(1);
             res.append(&mut binary_write_string(&n));
-            res.append(&mut binary_write_type(&SpType::Reg(t.clone())));
+            res.append(&mut binary_write_type(&SpType::Reg(t.clone()))?);
         }, None => res.push(0)
     }
-    res.append(&mut binary_write_expr(&l.code.tree));
-    res
+    res.append(&mut binary_write_expr(&l.code.tree)?);
+    Ok(res)
 }
 
 fn binary_parse_attr(b: &[u8], index: &mut usize) -> Option<Attr> {
@@ -354,14 +682,14 @@ fn binary_parse_attr(b: &[u8], index: &mut usize) -> Option<Attr> {
     *index += 1;
     Some(Attr {name, attrType, default, flag} )
 }
-fn binary_write_attr(a: &Attr) -> Vec<u8> {
+fn binary_write_attr(a: &Attr) -> Result<Vec<u8>, String> {
     let mut res = vec![];
     res.append(&mut binary_write_string(&a.name));
-    res.append(&mut binary_write_type(&a.attrType));
+    res.append(&mut binary_write_type(&a.attrType)?);
     match &a.default {
         Some(l) => {
             res.push(1);
-            res.append(&mut binary_write_lambda(&l));
+            res.append(&mut binary_write_lambda(&l)?);
         }, None => res.push(0)
     }
     res.push(match &a.flag {
@@ -370,15 +698,26 @@ fn binary_write_attr(a: &Attr) -> Vec<u8> {
         AttrFlag::Global => 2,
         AttrFlag::Unique => 3
     });
-    res
+    Ok(res)
 }
 
 impl DBState {
     pub fn new() -> DBState {
-        DBState { header: vec![], data: HashMap::new(), ref_list: HashMap::new() }
+        DBState { header: vec![], data: HashMap::new(), ref_list: HashMap::new(), globals: HashMap::new(), types: vec![], funcs: vec![] }
+    }
+    pub fn func(&self, name: &str) -> Option<&Lambda> {
+        self.funcs.iter().find(|x| x.named.as_ref().map_or(false, |(n, _)| n == name))
+    }
+    pub fn row_count(&self, ent: usize) -> usize {
+        for i in 0..self.header[ent].1.len() {
+            if let Some(v) = self.data.get(&(ent as u64, i as u64)) {
+                return v.len();
+            }
+        }
+        0
     }
     pub fn from_file(filename: &str) -> Option<Self> {
-        let mut res = DBState { header: vec![], data: HashMap::new(), ref_list: HashMap::new() };
+        let mut res = DBState::new();
         let bytestream = std::fs::read(filename).ok()?;
         let mut file_pos: usize = 0;
         while *bytestream.get(file_pos)? != 0 {
@@ -418,14 +757,46 @@ impl DBState {
             file_pos += 8;
             res.ref_list.insert((c1, c2), cnt);
         }
+        // Files written before global attributes were supported end after the reference list.
+        if file_pos == bytestream.len() {
+            return Some(res);
+        }
+        let globals_size = u64::from_le_bytes(bytestream.get(file_pos..file_pos + 8)?.try_into().ok()?);
+        file_pos += 8;
+        for _ in 0..globals_size {
+            let c1 = u64::from_le_bytes(bytestream.get(file_pos..file_pos + 8)?.try_into().ok()?);
+            file_pos += 8;
+            let c2 = u64::from_le_bytes(bytestream.get(file_pos..file_pos + 8)?.try_into().ok()?);
+            file_pos += 8;
+            res.globals.insert((c1, c2), binary_read_expr(&bytestream, &mut file_pos)?);
+        }
+        // Files written before type aliases were supported end after the globals.
+        if file_pos == bytestream.len() {
+            return Some(res);
+        }
+        let types_size = u64::from_le_bytes(bytestream.get(file_pos..file_pos + 8)?.try_into().ok()?);
+        file_pos += 8;
+        for _ in 0..types_size {
+            let name = binary_read_string(&bytestream, &mut file_pos)?;
+            res.types.push((name, binary_read_type(&bytestream, &mut file_pos)?));
+        }
+        // Files written before function definitions were supported end after the type aliases.
+        if file_pos == bytestream.len() {
+            return Some(res);
+        }
+        let funcs_size = u64::from_le_bytes(bytestream.get(file_pos..file_pos + 8)?.try_into().ok()?);
+        file_pos += 8;
+        for _ in 0..funcs_size {
+            res.funcs.push(binary_parse_lambda(&bytestream, &mut file_pos)?);
+        }
         Some(res)
     }
-    pub fn to_file(&self, filename: &str) -> Result<(), std::io::Error> {
+    pub fn to_file(&self, filename: &str) -> Result<(), String> {
         let mut res = vec![];
         for i in &self.header {
             res.append(&mut binary_write_string(&i.0));
             for j in &i.1 {
-                res.append(&mut binary_write_attr(j));
+                res.append(&mut binary_write_attr(j)?);
             }
             res.push(0);
         }
@@ -436,7 +807,7 @@ impl DBState {
             res.append(&mut u64::to_le_bytes(i.0.1).to_vec());
             res.append(&mut i.1.len().to_le_bytes().to_vec());
             for j in i.1 {
-                res.append(&mut binary_write_expr(&j.tree));
+                res.append(&mut binary_write_expr(&j.tree)?);
             }
         }
         res.append(&mut (self.ref_list.len() as u64).to_le_bytes().to_vec());
@@ -445,7 +816,22 @@ impl DBState {
             res.append(&mut u64::to_le_bytes(*v2).to_vec());
             res.append(&mut u64::to_le_bytes(*cnt).to_vec());
         }
-        fs::write(filename, res)?;
+        res.append(&mut (self.globals.len() as u64).to_le_bytes().to_vec());
+        for ((v1, v2), val) in &self.globals {
+            res.append(&mut u64::to_le_bytes(*v1).to_vec());
+            res.append(&mut u64::to_le_bytes(*v2).to_vec());
+            res.append(&mut binary_write_expr(&val.tree)?);
+        }
+        res.append(&mut (self.types.len() as u64).to_le_bytes().to_vec());
+        for (name, t) in &self.types {
+            res.append(&mut binary_write_string(name));
+            res.append(&mut binary_write_type(t)?);
+        }
+        res.append(&mut (self.funcs.len() as u64).to_le_bytes().to_vec());
+        for lm in &self.funcs {
+            res.append(&mut binary_write_lambda(lm)?);
+        }
+        fs::write(filename, res).map_err(|x| x.to_string())?;
         Ok(())
     }
 }
\ No newline at end of file
@@ -1,4 +1,4 @@
-use crate::tree::{self, ExprTree};
+use crate::tree::{self, ExprTree, LexerIterator};
 
 fn bracket_searcher(open_br: &str, close_br: &str, code: &Vec<(String, u64, u64)>, index: &mut u32,
     end_ln: u64, end_col: u64) -> Result<Vec<(String, u64, u64)>, String> {
@@ -18,6 +18,71 @@ fn bracket_searcher(open_br: &str, close_br: &str, code: &Vec<(String, u64, u64)
     }
     Err(format!("Expected '{}' in expression at ({}, {}).", close_br, end_ln, end_col))
 }
+fn split_arms(code: &Vec<(String, u64, u64)>) -> Vec<Vec<(String, u64, u64)>> {
+    let mut res = vec![vec![]];
+    let mut depth = 0;
+    for v in code {
+        if depth == 0 && v.0 == "," {
+            res.push(vec![]);
+            continue;
+        } else if v.0 == "(" || v.0 == "[" || v.0 == "{" || v.0 == "let" { depth += 1; }
+        else if v.0 == ")" || v.0 == "]" || v.0 == "}" || v.0 == "in" { depth -= 1; }
+        res.last_mut().unwrap().push(v.clone());
+    }
+    res
+}
+fn parse_arm(arm: &Vec<(String, u64, u64)>, end_ln: u64, end_cl: u64) -> Result<(tree::Pattern, Box<tree::Node>), String> {
+    let mut index = 0;
+    if arm.len() == 0 {
+        return Err(format!["Expected match arm at ({}, {}).", end_ln, end_cl]);
+    }
+    let (lx, ln, cl) = &arm[0];
+    let pattern = if lx == "Just" {
+        index += 1;
+        grammar_expect("(", arm, &mut index, end_ln, end_cl)?;
+        let name = parse_lit(arm, &mut index, end_ln, end_cl)?;
+        grammar_expect(")", arm, &mut index, end_ln, end_cl)?;
+        match name {
+            ExprTree::Ident(n) => tree::Pattern::Just(n),
+            _ => return Err(format!["Expected identifier in 'Just' pattern at ({}, {}).", ln, cl])
+        }
+    } else if lx == "Nothing" {
+        index += 1;
+        tree::Pattern::Nothing
+    } else if lx == "_" {
+        index += 1;
+        tree::Pattern::Wildcard
+    } else if lx == ":" {
+        index += 1;
+        grammar_expect(":", arm, &mut index, end_ln, end_cl)?;
+        let (lx2, ln2, cl2) = arm.get(index as usize).ok_or(format!["Expected variant index at ({}, {})", end_ln, end_cl])?;
+        let variant = lx2.parse::<u64>().map_err(|_| format!["Expected variant index, found '{}' at ({}, {}).", lx2, ln2, cl2])?;
+        index += 1;
+        grammar_expect("(", arm, &mut index, end_ln, end_cl)?;
+        let name = parse_lit(arm, &mut index, end_ln, end_cl)?;
+        grammar_expect(")", arm, &mut index, end_ln, end_cl)?;
+        match name {
+            ExprTree::Ident(n) => tree::Pattern::Variant(variant, n),
+            _ => return Err(format!["Expected identifier in variant pattern at ({}, {}).", ln, cl])
+        }
+    } else if lx.chars().nth(0).map_or(false, |c| c.is_alphabetic()) {
+        index += 1;
+        grammar_expect(":", arm, &mut index, end_ln, end_cl)?;
+        let (_, ln2, cl2) = arm.get(index as usize).ok_or(format!["Expected type at ({}, {})", end_ln, end_cl])?;
+        let mut type_toks = tree::TokenList { tokens: arm, index: index as u64 };
+        let t = match tree::SpType::parse_type(&mut type_toks)? {
+            tree::SpType::Reg(r) => r,
+            _ => return Err(format!["Special type not allowed at ({}, {})", ln2, cl2])
+        };
+        index = type_toks.index as u32;
+        tree::Pattern::Typed(lx.clone(), t)
+    } else {
+        return Err(format!["Expected pattern, found '{}' at ({}, {}).", lx, ln, cl]);
+    };
+    grammar_expect("-", arm, &mut index, end_ln, end_cl)?;
+    grammar_expect(">", arm, &mut index, end_ln, end_cl)?;
+    Ok((pattern, Box::new(grammar_parser("E1", arm, &mut index, end_ln, end_cl)?)))
+}
 fn grammar_expect(tok: &str, code: &Vec<(String, u64, u64)>, index: &mut u32, end_ln: u64, end_cl: u64) -> Result<(), String> {
     if *index >= code.len() as u32 { 
         return Err(format!["Expected '{}' at ({}, {})", tok, end_ln, end_cl]);
@@ -79,9 +144,48 @@ fn parse_lit(code: &Vec<(String, u64, u64)>, index: &mut u32, end_ln: u64, end_c
             tree::SpType::Reg(r) => r,
             _ => return Err(format!["Special type not allowed at ({}, {})", ln, cl])
         };
-        *index += type_toks.index as u32;
+        *index = type_toks.index as u32;
         return Ok(ExprTree::NothingLit(t)); 
     }
+    if lx == "Sum" {
+        *index -= 1;
+        let mut type_toks = tree::TokenList { tokens: code, index: *index as u64 };
+        let t = match tree::SpType::parse_type(&mut type_toks)? {
+            tree::SpType::Reg(r) => r,
+            _ => return Err(format!["Special type not allowed at ({}, {})", ln, cl])
+        };
+        *index = type_toks.index as u32;
+        grammar_expect(":", code, index, end_ln, end_cl)?;
+        grammar_expect(":", code, index, end_ln, end_cl)?;
+        let (lx2, ln2, cl2) = code.get(*index as usize).ok_or(format!["Expected variant index at ({}, {})", end_ln, end_cl])?;
+        let variant = lx2.parse::<u64>().map_err(|_| format!["Expected variant index, found '{}' at ({}, {}).", lx2, ln2, cl2])?;
+        *index += 1;
+        grammar_expect("(", code, index, end_ln, end_cl)?;
+        let inner_vec = bracket_searcher("(", ")", code, index, end_ln, end_cl)?;
+        let res = grammar_parser("E1", &inner_vec, &mut 0, end_ln, end_cl)?;
+        grammar_expect(")", code, index, end_ln, end_cl)?;
+        return Ok(ExprTree::SumVal(t, variant, Box::new(res)));
+    }
+    if lx == "Record" {
+        grammar_expect("{", code, index, end_ln, end_cl)?;
+        let inner_vec = bracket_searcher("{", "}", code, index, end_ln, end_cl)?;
+        grammar_expect("}", code, index, end_ln, end_cl)?;
+        let mut fields: Vec<(String, Box<tree::Node>)> = vec![];
+        for field in split_arms(&inner_vec) {
+            let mut field_index = 0;
+            let name = match parse_lit(&field, &mut field_index, end_ln, end_cl)? {
+                ExprTree::Ident(n) => n,
+                _ => return Err(format!["Expected field name in record literal at ({}, {}).", ln, cl])
+            };
+            if fields.iter().any(|x| x.0 == name) {
+                return Err(format!["Duplicate field '{}' in record literal at ({}, {}).", name, ln, cl]);
+            }
+            grammar_expect(":", &field, &mut field_index, end_ln, end_cl)?;
+            fields.push((name, Box::new(grammar_parser("E1", &field, &mut field_index, end_ln, end_cl)?)));
+        }
+        fields.sort_by(|x, y| x.0.cmp(&y.0));
+        return Ok(ExprTree::RecordLit(fields));
+    }
     if lx == "Just" {
         grammar_expect("(", code, index, end_ln, end_cl)?;
         let inner_vec = bracket_searcher("(", ")", code, index, end_ln, end_cl)?;
@@ -99,8 +203,8 @@ fn parse_lit(code: &Vec<(String, u64, u64)>, index: &mut u32, end_ln: u64, end_c
             let mut inner_vec = Vec::new();
             while let Some(v) = code.iter().nth(*index as usize) {
                 if v.0 == "," && bracket_counter == 1 { break; }
-                else if v.0 == "(" && !is_array || v.0 == "[" && is_array { bracket_counter += 1; }
-                else if v.0 == ")" && !is_array || v.0 == "]" && is_array { 
+                else if v.0 == "(" || v.0 == "[" || v.0 == "{" { bracket_counter += 1; }
+                else if v.0 == ")" || v.0 == "]" || v.0 == "}" {
                     bracket_counter -= 1;
                     if bracket_counter == 0 {
                         break;
@@ -142,18 +246,79 @@ fn parse_lit(code: &Vec<(String, u64, u64)>, index: &mut u32, end_ln: u64, end_c
     }
     if lx == "for" {
         grammar_expect("(", code, index, end_ln, end_cl)?;
-        let (lx2, ln, cl) = &code[*index as usize];
-        if let Some(c) = lx2.chars().nth(0) {
-            if !c.is_alphabetic() {
+        let mut ents = vec![];
+        loop {
+            let (lx2, ln, cl) = code.get(*index as usize).ok_or(format!["Expected identifier at ({}, {}).", end_ln, end_cl])?;
+            if !lx2.chars().nth(0).map_or(false, |c| c.is_alphabetic()) {
                 return Err(format!["Expected identifier at ({}, {}), found '{}'.", ln, cl, lx2]);
             }
+            ents.push(lx2.clone());
+            *index += 1;
+            match code.get(*index as usize) {
+                Some((s, _, _)) if s == "," => *index += 1,
+                _ => break
+            }
         }
-        *index += 1;
         grammar_expect(")", code, index, end_ln, end_cl)?;
         let mut type_toks = tree::TokenList { tokens: code, index: *index as u64 };
         let t = tree::Lambda::parse_lambda(&mut type_toks)?;
+        let mut clauses = tree::ForClauses::new();
+        if type_toks.lookahead() == Some("order".to_string()) {
+            tree::expect(&mut type_toks, vec!["order", "by"])?;
+            let key = tree::Lambda::parse_lambda(&mut type_toks)?;
+            let desc = type_toks.lookahead() == Some("desc".to_string());
+            if desc || type_toks.lookahead() == Some("asc".to_string()) {
+                type_toks.next();
+            }
+            clauses.order = Some((key, desc));
+        }
         *index = type_toks.index as u32;
-        return Ok(ExprTree::For(lx2.clone(), Box::new(t)));
+        for (clause, val) in [("limit", &mut clauses.limit), ("offset", &mut clauses.offset)] {
+            if code.get(*index as usize).map_or(false, |x| x.0 == clause) {
+                *index += 1;
+                *val = Some(grammar_parser("E5", code, index, end_ln, end_cl)?);
+            }
+        }
+        return Ok(ExprTree::For(ents, Box::new(t), Box::new(clauses)));
+    }
+    if lx == "group" {
+        grammar_expect("(", code, index, end_ln, end_cl)?;
+        let (lx2, ln, cl) = code.get(*index as usize).ok_or(format!["Expected identifier at ({}, {}).", end_ln, end_cl])?;
+        if !lx2.chars().nth(0).map_or(false, |c| c.is_alphabetic()) {
+            return Err(format!["Expected identifier at ({}, {}), found '{}'.", ln, cl, lx2]);
+        }
+        *index += 1;
+        grammar_expect(")", code, index, end_ln, end_cl)?;
+        grammar_expect("by", code, index, end_ln, end_cl)?;
+        let mut toks = tree::TokenList { tokens: code, index: *index as u64 };
+        let key = tree::Lambda::parse_lambda(&mut toks)?;
+        tree::expect(&mut toks, vec!["{"])?;
+        let mut aggs: Vec<(String, String, Option<Box<tree::Lambda>>)> = vec![];
+        let coords = |i: u64| code.get(i as usize).map_or((end_ln, end_cl), |x| (x.1, x.2));
+        loop {
+            let (ln, cl) = coords(toks.index);
+            let name = tree::read_ident(&mut toks)?;
+            if name == "key" || aggs.iter().any(|x| x.0 == name) {
+                return Err(format!["Duplicate field '{}' in group at ({}, {}).", name, ln, cl]);
+            }
+            tree::expect(&mut toks, vec![":"])?;
+            let (ln, cl) = coords(toks.index);
+            let agg = tree::read_ident(&mut toks)?;
+            let arg = match agg.as_str() {
+                "count" => None,
+                "sum" | "avg" | "min" | "max" => Some(Box::new(tree::Lambda::parse_lambda(&mut toks)?)),
+                _ => return Err(format!["Unknown aggregate '{}' at ({}, {}).", agg, ln, cl])
+            };
+            aggs.push((name, agg, arg));
+            match toks.next() {
+                Some((s, _, _)) if s == "," => {},
+                Some((s, _, _)) if s == "}" => break,
+                Some((s, ln, cl)) => return Err(format!["Expected ',' or '}}', found '{}' at ({}, {}).", s, ln, cl]),
+                None => return Err(format!["Expected '}}' at ({}, {}).", end_ln, end_cl])
+            }
+        }
+        *index = toks.index as u32;
+        return Ok(ExprTree::Group(lx2.clone(), Box::new(key), aggs));
     }
     if let Some(c) = lx.chars().nth(0) {
         if c.is_alphabetic() {
@@ -188,13 +353,41 @@ fn grammar_parser(nonterm: &str, code: &Vec<(String, u64, u64)>, index: &mut u32
     -> Result<tree::Node, String> {
     match nonterm {
         "E1" => {
-            let val1 = grammar_parser("E2", code, index, end_ln, end_cl)?;
+            let mut res = grammar_parser("E2", code, index, end_ln, end_cl)?;
+            while let Some((a, _, _)) = code.iter().nth(*index as usize) {
+                if a == "|" {
+                    let (_, ln, cl) = code[*index as usize];
+                    *index += 1;
+                    grammar_expect("|", code, index, end_ln, end_cl)?;
+                    let val = grammar_parser("E2", code, index, end_ln, end_cl)?;
+                    res = tree::Node { tree: ExprTree::Or(Box::new(res), Box::new(val)), ln: ln, col: cl };
+                } else { break; }
+            }
+            match code.iter().nth(*index as usize) {
+                Some((tok, ln, cl)) => Err(format!["Unexpected token '{}' in expression at ({}, {})",
+                    tok, ln, cl]),
+                None => Ok(res)
+            }
+        }, "E2" => {
+            let mut res = grammar_parser("E3", code, index, end_ln, end_cl)?;
+            while let Some((a, _, _)) = code.iter().nth(*index as usize) {
+                if a == "&" {
+                    let (_, ln, cl) = code[*index as usize];
+                    *index += 1;
+                    grammar_expect("&", code, index, end_ln, end_cl)?;
+                    let val = grammar_parser("E3", code, index, end_ln, end_cl)?;
+                    res = tree::Node { tree: ExprTree::And(Box::new(res), Box::new(val)), ln: ln, col: cl };
+                } else { break; }
+            }
+            return Ok(res);
+        }, "E3" => {
+            let val1 = grammar_parser("E4", code, index, end_ln, end_cl)?;
             match code.iter().nth(*index as usize) {
                 Some((a,_,_)) if a == "=" => {
                     let (_, ln, cl) = code[*index as usize];
                     *index += 1;
                     grammar_expect("=", code, index, end_ln, end_cl)?;
-                    let val2 = grammar_parser("E2", code, index, end_ln, end_cl)?;
+                    let val2 = grammar_parser("E4", code, index, end_ln, end_cl)?;
                     return Ok(tree::Node {
                         tree: ExprTree::Eq(true, Box::new(val1), Box::new(val2)),
                         col: cl,
@@ -204,18 +397,16 @@ fn grammar_parser(nonterm: &str, code: &Vec<(String, u64, u64)>, index: &mut u32
                     let (_, ln, cl) = code[*index as usize];
                     *index += 1;
                     grammar_expect("=", code, index, end_ln, end_cl)?;
-                    let val2 = grammar_parser("E2", code, index, end_ln, end_cl)?;
+                    let val2 = grammar_parser("E4", code, index, end_ln, end_cl)?;
                     return Ok(tree::Node {
                         tree: ExprTree::Eq(false, Box::new(val1), Box::new(val2)),
                         col: cl,
                         ln: ln
                     });
-                }, Some((tok, ln, cl)) => Err(format!["Unexpected token '{}' in expression at ({}, {})",
-                    tok, ln, cl]),
-                None => Ok(val1)
+                }, _ => Ok(val1)
             }
-        }, "E2" => {
-            let val1 = grammar_parser("E3", code, index, end_ln, end_cl)?;
+        }, "E4" => {
+            let val1 = grammar_parser("E5", code, index, end_ln, end_cl)?;
             match code.iter().nth(*index as usize) {
                 Some((a,_,_)) if "<>".contains(a) => {
                     let (_, ln, cl) = code[*index as usize];
@@ -226,7 +417,7 @@ fn grammar_parser(nonterm: &str, code: &Vec<(String, u64, u64)>, index: &mut u32
                         Some((b,_,_)) if b == "=" => {is_eq = true; *index += 1;},
                         Some(_) | None => {}
                     }
-                    let val2 = grammar_parser("E3", code, index, end_ln, end_cl)?;
+                    let val2 = grammar_parser("E5", code, index, end_ln, end_cl)?;
                     return Ok(tree::Node {
                         tree: ExprTree::Cmp(is_greater, is_eq, Box::new(val1), Box::new(val2)),
                         col: cl,
@@ -234,13 +425,13 @@ fn grammar_parser(nonterm: &str, code: &Vec<(String, u64, u64)>, index: &mut u32
                     });
                 }, _ => Ok(val1)
             }
-        }, "E3" => {
-            let mut res = grammar_parser("E4", code, index, end_ln, end_cl)?;
+        }, "E5" => {
+            let mut res = grammar_parser("E6", code, index, end_ln, end_cl)?;
             while let Some((a, _, _)) = code.iter().nth(*index as usize) {
                 if a == "+" || a == "-" {
                     let (_, ln, cl) = code[*index as usize];
                     *index += 1;
-                    let val = grammar_parser("E4", code, index, end_ln, end_cl)?;
+                    let val = grammar_parser("E6", code, index, end_ln, end_cl)?;
                     res = tree::Node {
                         tree: if a == "+" {ExprTree::Plus(Box::new(res), Box::new(val))} 
                             else {ExprTree::Minus(Box::new(res), Box::new(val))},
@@ -250,33 +441,46 @@ fn grammar_parser(nonterm: &str, code: &Vec<(String, u64, u64)>, index: &mut u32
                 } else { break; }
             }
             return Ok(res);
-        }, "E4" => {
-            let mut res = grammar_parser("E5", code, index, end_ln, end_cl)?;
+        }, "E6" => {
+            let mut res = grammar_parser("E7", code, index, end_ln, end_cl)?;
             while let Some((a, _, _)) = code.iter().nth(*index as usize) {
                 if a == "*" || a == "/" || a == "%" {
                     let (_, ln, cl) = code[*index as usize];
                     *index += 1;
-                    let val = grammar_parser("E5", code, index, end_ln, end_cl)?;
+                    let val = grammar_parser("E7", code, index, end_ln, end_cl)?;
                     if a == "*" { res = tree::Node{ tree: ExprTree::Mul(Box::new(res), Box::new(val)), col: cl, ln: ln }; }
                     else if a == "/" { res = tree::Node{ tree: ExprTree::Div(Box::new(res), Box::new(val)), col: cl, ln: ln } }
                     else if a == "%" { res = tree::Node{ tree: ExprTree::Mod(Box::new(res), Box::new(val)), col: cl, ln: ln } }
                 } else { break; }
             }
             return Ok(res);
-        }, "E5" => {
-            let res = grammar_parser("E6", code, index, end_ln, end_cl)?;
+        }, "E7" => {
+            match code.iter().nth(*index as usize) {
+                Some((a, _, _)) if a == "!" || a == "-" => {
+                    let (_, ln, cl) = code[*index as usize];
+                    *index += 1;
+                    let val = grammar_parser("E7", code, index, end_ln, end_cl)?;
+                    return Ok(tree::Node {
+                        tree: if a == "!" {ExprTree::Not(Box::new(val))} else {ExprTree::Neg(Box::new(val))},
+                        col: cl,
+                        ln: ln
+                    });
+                }, Some(_) | None => Ok(grammar_parser("E8", code, index, end_ln, end_cl)?)
+            }
+        }, "E8" => {
+            let res = grammar_parser("E9", code, index, end_ln, end_cl)?;
             match code.iter().nth(*index as usize) {
                 Some((a, _, _)) if a == "^" => {
                     let (_, ln, cl) = code[*index as usize];
                     *index += 1;
                     return Ok(tree::Node{ 
-                        tree: ExprTree::Exp(Box::new(res), Box::new(grammar_parser("E6", code, index, end_ln, end_cl)?)),
+                        tree: ExprTree::Exp(Box::new(res), Box::new(grammar_parser("E7", code, index, end_ln, end_cl)?)),
                         col: cl,
                         ln: ln
                     });
                 }, Some(_) | None => return Ok(res)
             }
-        }, "E6" => {
+        }, "E9" => {
             match code.iter().nth(*index as usize) {
                 Some((a, _, _)) if a == "if" => {
                     let (_, ln, cl) = code[*index as usize];
@@ -293,9 +497,77 @@ fn grammar_parser(nonterm: &str, code: &Vec<(String, u64, u64)>, index: &mut u32
                         col: cl,
                         ln: ln
                     });
-                }, Some(_) | None => Ok(grammar_parser("E7", code, index, end_ln, end_cl)?)}
+                }, Some((a, _, _)) if a == "match" => {
+                    let (_, ln, cl) = code[*index as usize];
+                    grammar_expect("match", code, index, end_ln, end_cl)?;
+                    let mut inner_vec = vec![];
+                    let mut depth = 0;
+                    while let Some(v) = code.iter().nth(*index as usize) {
+                        if depth == 0 && v.0 == "{" { break; }
+                        else if v.0 == "(" || v.0 == "[" { depth += 1; }
+                        else if v.0 == ")" || v.0 == "]" { depth -= 1; }
+                        inner_vec.push(v.clone());
+                        *index += 1;
+                    }
+                    if inner_vec.len() == 0 {
+                        return Err(format!["Expected expression after 'match' at ({}, {}).", ln, cl]);
+                    }
+                    let val = grammar_parser("E1", &inner_vec, &mut 0, end_ln, end_cl)?;
+                    grammar_expect("{", code, index, end_ln, end_cl)?;
+                    let arms_vec = bracket_searcher("{", "}", code, index, end_ln, end_cl)?;
+                    grammar_expect("}", code, index, end_ln, end_cl)?;
+                    let mut arms = vec![];
+                    for arm in split_arms(&arms_vec) {
+                        arms.push(parse_arm(&arm, end_ln, end_cl)?);
+                    }
+                    return Ok(tree::Node {
+                        tree: ExprTree::Match(Box::new(val), arms),
+                        col: cl,
+                        ln: ln
+                    });
+                }, Some((a, _, _)) if a == "let" => {
+                    let (_, ln, cl) = code[*index as usize];
+                    grammar_expect("let", code, index, end_ln, end_cl)?;
+                    let mut bindings = vec![];
+                    loop {
+                        if *index >= code.len() as u32 {
+                            return Err(format!["Expected identifier in let binding at ({}, {}).", end_ln, end_cl]);
+                        }
+                        let (name, ln2, cl2) = &code[*index as usize];
+                        match name.chars().nth(0) {
+                            Some(c) if c.is_alphabetic() => {},
+                            _ => return Err(format!["Expected identifier, found '{}' in let binding at ({}, {}).", name, ln2, cl2])
+                        }
+                        *index += 1;
+                        grammar_expect("=", code, index, end_ln, end_cl)?;
+                        let mut inner_vec = vec![];
+                        let mut depth = 0;
+                        while let Some(v) = code.iter().nth(*index as usize) {
+                            if depth == 0 && (v.0 == "," || v.0 == "in") { break; }
+                            else if v.0 == "(" || v.0 == "[" || v.0 == "{" || v.0 == "let" { depth += 1; }
+                            else if v.0 == ")" || v.0 == "]" || v.0 == "}" || v.0 == "in" { depth -= 1; }
+                            inner_vec.push(v.clone());
+                            *index += 1;
+                        }
+                        if *index >= code.len() as u32 {
+                            return Err(format!["Expected 'in' in let expression at ({}, {}).", end_ln, end_cl]);
+                        }
+                        if inner_vec.len() == 0 {
+                            return Err(format!["Expected expression in let binding of '{}' at ({}, {}).", name, ln2, cl2]);
+                        }
+                        bindings.push((name.clone(), Box::new(grammar_parser("E1", &inner_vec, &mut 0, end_ln, end_cl)?)));
+                        *index += 1;
+                        if code[*index as usize - 1].0 == "in" { break; }
+                    }
+                    let body = grammar_parser("E1", code, index, end_ln, end_cl)?;
+                    return Ok(tree::Node {
+                        tree: ExprTree::Let(bindings, Box::new(body)),
+                        col: cl,
+                        ln: ln
+                    });
+                }, Some(_) | None => Ok(grammar_parser("E10", code, index, end_ln, end_cl)?)}
         }
-        "E7" => {
+        "E10" => {
             let mut res = match code.iter().nth(*index as usize) {
                 Some((a, _, _)) if a == "(" => {
                     *index += 1;
@@ -308,7 +580,7 @@ fn grammar_parser(nonterm: &str, code: &Vec<(String, u64, u64)>, index: &mut u32
                     *index += 1;
                     let mut type_toks = tree::TokenList { tokens: code, index: *index as u64 };
                     let t = tree::Lambda::parse_lambda(&mut type_toks)?;
-                    *index += type_toks.index as u32;
+                    *index = type_toks.index as u32;
                     tree::Node {
                         tree: ExprTree::LambdaExpr(Box::new(t)),
                         col: cl,
@@ -325,12 +597,56 @@ fn grammar_parser(nonterm: &str, code: &Vec<(String, u64, u64)>, index: &mut u32
                 if a == "." {
                     let (_, ln, cl) = code[*index as usize];
                     *index += 1;
-                    let (_, ln_lit, cl_lit) = code[*index as usize];
+                    let (lx_lit, ln_lit, cl_lit) = code.get(*index as usize).ok_or(
+                        format!["Expected identifier after '.' at ({}, {}).", end_ln, end_cl])?.clone();
+                    // The lexer reads nested projections like 't.0.1' as a double literal '0.1'.
+                    if let Some((s1, s2)) = lx_lit.split_once('.') {
+                        if let (Ok(i1), Ok(i2)) = (s1.parse::<i64>(), s2.parse::<i64>()) {
+                            *index += 1;
+                            // In 't.0.name' the lexer pads '0.' to '0.0'; the padding takes no room in the source.
+                            let padded = code.get(*index as usize).map_or(false, |x|
+                                x.1 == ln_lit && x.2 == cl_lit + s1.len() as u64 + 1);
+                            for i in if padded {vec![i1]} else {vec![i1, i2]} {
+                                res = tree::Node { tree: ExprTree::Dot(Box::new(res), Box::new(tree::Node {
+                                        tree: ExprTree::IntLit(i), col: cl_lit, ln: ln_lit})),
+                                    col: cl, ln: ln};
+                            }
+                            if padded {
+                                let (_, ln_f, cl_f) = code[*index as usize].clone();
+                                res = tree::Node { tree: ExprTree::Dot(Box::new(res), Box::new(tree::Node {
+                                        tree: parse_lit(code, index, end_ln, end_cl)?, col: cl_f, ln: ln_f})),
+                                    col: cl, ln: ln};
+                            }
+                            continue;
+                        }
+                    }
                     res = tree::Node { tree: ExprTree::Dot(Box::new(res), Box::new(tree::Node {
                             tree: parse_lit(code, index, end_ln, end_cl)?,
                             col: cl_lit,
                             ln: ln_lit})),
                         col: cl, ln: ln};
+                } else if a == "[" {
+                    let (_, ln, cl) = code[*index as usize];
+                    *index += 1;
+                    let inner_vec = bracket_searcher("[", "]", code, index, end_ln, end_cl)?;
+                    grammar_expect("]", code, index, end_ln, end_cl)?;
+                    let mut depth = 0;
+                    let mut colon = None;
+                    for (i, v) in inner_vec.iter().enumerate() {
+                        if v.0 == "(" || v.0 == "[" || v.0 == "{" { depth += 1; }
+                        else if v.0 == ")" || v.0 == "]" || v.0 == "}" { depth -= 1; }
+                        else if v.0 == ":" && depth == 0 { colon = Some(i); break; }
+                    }
+                    res = match colon {
+                        None => tree::Node { tree: ExprTree::Index(Box::new(res),
+                            Box::new(tree::Node::new(inner_vec, ln, cl)?)), ln: ln, col: cl },
+                        Some(i) => {
+                            let (from, to) = (inner_vec[..i].to_vec(), inner_vec[i + 1..].to_vec());
+                            let from = if from.len() == 0 {None} else {Some(Box::new(tree::Node::new(from, ln, cl)?))};
+                            let to = if to.len() == 0 {None} else {Some(Box::new(tree::Node::new(to, ln, cl)?))};
+                            tree::Node { tree: ExprTree::Slice(Box::new(res), from, to), ln: ln, col: cl }
+                        }
+                    };
                 } else if a == "(" {
                     let mut arg_vec = Vec::new();
                     let (_, ln, cl) = code[*index as usize];
@@ -343,8 +659,8 @@ fn grammar_parser(nonterm: &str, code: &Vec<(String, u64, u64)>, index: &mut u32
                             if v.0 == "," && bracket_counter == 1 { 
                                 do_exit = false;
                                 break; }
-                            else if v.0 == "(" { bracket_counter += 1; }
-                            else if v.0 == ")" {
+                            else if v.0 == "(" || v.0 == "[" || v.0 == "{" { bracket_counter += 1; }
+                            else if v.0 == ")" || v.0 == "]" || v.0 == "}" {
                                 bracket_counter -= 1;
                                 if bracket_counter == 0 {
                                     do_exit = true;
@@ -6,6 +6,7 @@ mod command_parser;
 mod semantic;
 mod run;
 mod compute;
+mod stdlib;
 
 fn main() {
     let args: Vec<String> = std::env::args().collect();
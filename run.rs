@@ -2,13 +2,137 @@ use std::collections::{HashMap, HashSet};
 
 use crate::{compute::{compute, find_refs}, tree::{self, ExprTree, Type, Node}};
 
+// Advances a tuple of row indexes to the next combination, returning false once all have been visited.
+fn next_index(rows: &mut Vec<usize>, sizes: &Vec<usize>) -> bool {
+    for k in (0..rows.len()).rev() {
+        rows[k] += 1;
+        if rows[k] < sizes[k] {
+            return true;
+        }
+        rows[k] = 0;
+    }
+    false
+}
+fn add_ref_counts(db: &mut tree::DBState, expr: &ExprTree) {
+    for rf in find_refs(expr) {
+        if db.ref_list.contains_key(&rf) {
+            *db.ref_list.get_mut(&rf).unwrap() += 1;
+        } else {
+            db.ref_list.insert(rf, 1);
+        }
+    }
+}
+fn combined_header(db: &tree::DBState, ents: &Vec<usize>) -> Result<(Vec<tree::Attr>, Vec<(usize, usize)>), String> {
+    let mut attrs = vec![];
+    let mut sources = vec![];
+    let mut name_counts: HashMap<String, u64> = HashMap::new();
+    for e in ents {
+        for a in &db.header[*e].1 {
+            *name_counts.entry(a.name.clone()).or_insert(0) += 1;
+        }
+    }
+    for (k, e) in ents.iter().enumerate() {
+        // Repeated entities are told apart by their occurrence number.
+        let mut prefix = db.header[*e].0.clone();
+        if ents.iter().filter(|x| *x == e).count() > 1 {
+            prefix = format!["{}_{}", prefix, ents[..=k].iter().filter(|x| *x == e).count()];
+        }
+        for (i, a) in db.header[*e].1.iter().enumerate() {
+            if let tree::AttrFlag::Computable | tree::AttrFlag::Global = a.flag {
+                continue;
+            }
+            let mut new_attr = a.clone();
+            if name_counts[&a.name] > 1 {
+                new_attr.name = format!["{}_{}", prefix, a.name];
+            }
+            if attrs.iter().any(|x: &tree::Attr| x.name == new_attr.name) {
+                return Err(format!["attribute name '{}' occurs more than once in the resulting entity.", new_attr.name]);
+            }
+            // Rows of the sources may be repeated in the result, so uniqueness is not preserved.
+            if new_attr.flag == tree::AttrFlag::Unique {
+                new_attr.flag = tree::AttrFlag::None;
+            }
+            attrs.push(new_attr);
+            sources.push((k, i));
+        }
+    }
+    Ok((attrs, sources))
+}
+
+fn same_sptype(t1: &tree::SpType, t2: &tree::SpType) -> bool {
+    match (t1, t2) {
+        (tree::SpType::Reg(a), tree::SpType::Reg(b)) => a == b,
+        (tree::SpType::Gen(a, _, _), tree::SpType::Gen(b, _, _)) | (tree::SpType::Restrict(a, _), tree::SpType::Restrict(b, _)) => a == b,
+        _ => false
+    }
+}
+// Stored attributes of the first entity with the positions of the same attributes in the second one.
+fn matching_header(db: &tree::DBState, e1: usize, e2: usize) -> Result<(Vec<tree::Attr>, Vec<(usize, usize)>), String> {
+    let stored = |e: usize| db.header[e].1.iter().enumerate().filter(|(_, a)|
+        a.flag != tree::AttrFlag::Computable && a.flag != tree::AttrFlag::Global).collect::<Vec<_>>();
+    let (attrs1, attrs2) = (stored(e1), stored(e2));
+    if attrs1.len() != attrs2.len() {
+        return Err("the entities have different numbers of attributes.".to_string());
+    }
+    let mut columns = vec![];
+    for (i, a) in &attrs1 {
+        match attrs2.iter().find(|(_, b)| b.name == a.name) {
+            Some((j, b)) if same_sptype(&a.attrType, &b.attrType) => columns.push((*i, *j)),
+            Some(_) => return Err(format!["attribute '{}' has different types.", a.name]),
+            None => return Err(format!["attribute '{}' is missing in entity '{}'.", a.name, db.header[e2].0])
+        }
+    }
+    Ok((attrs1.into_iter().map(|(_, a)| a.clone()).collect(), columns))
+}
+fn entity_rows(db: &tree::DBState, e: usize, columns: &Vec<usize>) -> Vec<Vec<Node>> {
+    (0..db.row_count(e)).map(|r| columns.iter().map(|c| db.data[&(e as u64, *c as u64)][r].clone()).collect()).collect()
+}
+// A key that is the same for values equal by '==', so values only have to be compared with the ones sharing their key.
+fn value_key(v: &ExprTree) -> String {
+    let list = |v: &Vec<Box<Node>>| v.iter().map(|x| value_key(&x.tree)).collect::<Vec<_>>().join(", ");
+    match v {
+        // 0.0 and -0.0 are equal, but printed differently.
+        ExprTree::DoubleLit(d) if *d == 0.0 => "0".to_string(),
+        ExprTree::ArrayLit(v, _) | ExprTree::TupleLit(v) => format!["({})", list(v)],
+        ExprTree::JustLit(x) => format!["Just({})", value_key(&x.tree)],
+        ExprTree::SumVal(_, i, x) => format!["::{}({})", i, value_key(&x.tree)],
+        ExprTree::RecordLit(v) => format!["{{{}}}", v.iter().map(|(n, x)| format!["{}: {}", n, value_key(&x.tree)]).collect::<Vec<_>>().join(", ")],
+        ExprTree::Ref(_, _, _, ent, pos) => format!["#{}:{}", ent, pos],
+        _ => Node::simple(v.clone()).to_string()
+    }
+}
+// Groups the rows by the keys of their values; equal rows always end up in the same group.
+fn group_rows(rows: &Vec<Vec<Node>>) -> HashMap<Vec<String>, Vec<usize>> {
+    let mut groups: HashMap<Vec<String>, Vec<usize>> = HashMap::new();
+    for (i, r) in rows.iter().enumerate() {
+        groups.entry(r.iter().map(|x| value_key(&x.tree)).collect()).or_insert(vec![]).push(i);
+    }
+    groups
+}
+// Whether 'rows' contains 'row', comparing it only with the rows of its group.
+fn contains_row(rows: &Vec<Vec<Node>>, groups: &HashMap<Vec<String>, Vec<usize>>, row: &Vec<Node>, db: &tree::DBState) -> Result<bool, String> {
+    let key: Vec<String> = row.iter().map(|x| value_key(&x.tree)).collect();
+    'rows: for r in groups.get(&key).into_iter().flatten().map(|i| &rows[*i]) {
+        for (x, y) in r.iter().zip(row.iter()) {
+            match compute(&ExprTree::Eq(true, Box::new(x.clone()), Box::new(y.clone())), db, &HashMap::new())? {
+                ExprTree::BoolLit(true) => {},
+                _ => continue 'rows
+            }
+        }
+        return Ok(true);
+    }
+    Ok(false)
+}
+
 impl tree::ComNode {
     pub fn complete(&mut self, mut db: tree::DBState) -> Result<tree::DBState, String> {
-        match &self.cmd {
+        self.cmd.resolve(&db)?;
+        // Checking runs on the command itself, so the parameter types it infers are kept for evaluation.
+        match &mut self.cmd {
             tree::Command::Open(name) => {
                 tree::DBState::from_file(&name).ok_or(format!["({}, {}): error opening file '{}'", self.ln, self.col, name])
             }, tree::Command::Commit(name) => {
-                db.to_file(&name).map_err(|x| x.to_string())?;
+                db.to_file(&name).map_err(|x| format!["({}, {}): {}", self.ln, self.col, x])?;
                 Ok(db)
             }, tree::Command::NewEntity(name, attrs) => {
                 match db.header.iter().position(|x| x.0 == *name) {
@@ -20,18 +144,52 @@ impl tree::ComNode {
                                 return Err(format!["({}, {}): unable to create entity '{}' with identical attribute names '{}'.",
                                     self.ln, self.col, name, i.name]);
                             }
-                            if let Some(_) = i.default {
+                            if let (Some(_), tree::AttrFlag::None | tree::AttrFlag::Unique) = (&i.default, &i.flag) {
                                 return Err(format!["({}, {}): default values only allowed in new attributes in Reshape.", self.ln, self.col]);
                             }
                             attr_names.insert(i.name.clone());
                         }
                         db.header.push((name.clone(), attrs.to_vec()));
+                        for i in attrs.iter_mut().filter(|x| x.flag == tree::AttrFlag::Computable) {
+                            let needed_type = Type::FuncType(vec![Box::new(Type::Object(tree::CoordStr::new(name.clone()))),
+                                Box::new(match &i.attrType {
+                                    tree::SpType::Reg(t) => t.clone(),
+                                    _ => return Err(format!["({}, {}): computable attribute '{}' of entity '{}' cannot have a Gen or Restrict type.",
+                                        self.ln, self.col, i.name, name])
+                                })]);
+                            if i.default.as_mut().unwrap().get_type(&db, &HashMap::new())? != needed_type {
+                                return Err(format!["({}, {}): defining lambda of computable attribute '{}' of entity '{}' doesn't have type '{:?}'.",
+                                    self.ln, self.col, i.name, name, needed_type]);
+                            }
+                        }
+                        db.header.last_mut().unwrap().1 = attrs.to_vec();
                         Ok(db)
                     }
                 }
             }, tree::Command::Drop(ent) => {
                 match db.header.iter().position(|x| x.0 == *ent) {
                     Some(pos) => {
+                        // Stored functions, computable attributes and global values are checked again without the entity.
+                        db.header[pos].0 = String::new();
+                        for lm in db.funcs.clone().iter_mut() {
+                            if lm.get_type(&db, &HashMap::new()).is_err() {
+                                return Err(format!["({}, {}): unable to drop '{}' as function '{}' depends on it.",
+                                    self.ln, self.col, ent, lm.named.clone().unwrap().0]);
+                            }
+                        }
+                        for (e, (ent_name, attrs)) in db.header.iter().enumerate().filter(|(e, _)| *e != pos) {
+                            for (a, atr) in attrs.iter().enumerate() {
+                                let failed = match (&atr.flag, &atr.default, db.globals.get(&(e as u64, a as u64))) {
+                                    (tree::AttrFlag::Computable, Some(lm), _) => lm.clone().get_type(&db, &HashMap::new()).is_err(),
+                                    (tree::AttrFlag::Global, _, Some(val)) => val.clone().get_type(&db, &HashMap::new()).is_err(),
+                                    _ => false
+                                };
+                                if failed {
+                                    return Err(format!["({}, {}): unable to drop '{}' as attribute '{}' of entity '{}' depends on it.",
+                                        self.ln, self.col, ent, atr.name, ent_name]);
+                                }
+                            }
+                        }
                         db.header.swap_remove(pos);
                         let mut rem_v = vec![];
                         for ((ent,ht_pos),_) in db.data.iter().by_ref() {
@@ -55,6 +213,17 @@ impl tree::ComNode {
                             }
                             db.data.remove(&i);
                         }
+                        let rem_g: Vec<_> = db.globals.keys().filter(|x| x.0 == pos as u64).cloned().collect();
+                        for i in rem_g {
+                            for k in find_refs(&db.globals[&i].tree) {
+                                if db.ref_list[&k] == 1 {
+                                    db.ref_list.remove(&k);
+                                } else {
+                                    *db.ref_list.get_mut(&k).unwrap() -= 1;
+                                }
+                            }
+                            db.globals.remove(&i);
+                        }
                         Ok(db)
                     },
                     None => Err(format!["({}, {}): unable to drop a non-recognized entity '{}'.", self.ln, self.col, ent])
@@ -75,9 +244,10 @@ impl tree::ComNode {
                         return Err(format!["({}, {}): unable to add values to global/computable attribute '{}'.", self.ln, self.col, 
                             ent_attrs[cur_attr].name]);
                     }
-                    for i in data {
+                    for i in data.iter_mut() {
+                        i.get_type(&db, &HashMap::new())?;
                         let i_value = compute(&i.tree, &db, &HashMap::new())?;
-                        let i_type = Node::simple(i_value).get_type(&db, &HashMap::new())?;
+                        let i_type = Node::simple(i_value.clone()).get_type(&db, &HashMap::new())?;
                         match &ent_attrs[cur_attr].attrType {
                             tree::SpType::Reg(t) => if *t != i_type {
                                 return Err(format!["({}, {}): unable to add a value of type '{:?}' to attribute '{}' of type '{:?}'.", self.ln, self.col,
@@ -90,7 +260,7 @@ impl tree::ComNode {
                                         i_type, attr, t]);
                                 } else {
                                     match compute(&ExprTree::Call(Box::new(Node::simple(ExprTree::LambdaExpr(Box::new(p.clone())))),
-                                        vec![Box::new(i.clone())]), &db, &HashMap::new())? {
+                                        vec![Box::new(Node::simple(i_value.clone()))]), &db, &HashMap::new())? {
                                             ExprTree::BoolLit(true) => {},
                                             _ => return Err(format!["({}, {}): unable to add value not satisfying restriction of attribute '{}'", self.ln, self.col,
                                                 attr])
@@ -98,7 +268,7 @@ impl tree::ComNode {
                                 }
                             }
                         }
-                        new_vals[cur_attr].push(i.clone());
+                        new_vals[cur_attr].push(Node::simple(i_value));
                     }
                     if num_of_vals == -1 {
                         num_of_vals = data.len() as i32;
@@ -106,8 +276,7 @@ impl tree::ComNode {
                 }
                 for i in 0..new_vals.len() {
                     match ent_attrs[i].flag {
-                        tree::AttrFlag::Computable => return Err(format!["Computable attributes not supported."]),
-                        tree::AttrFlag::Global => continue,
+                        tree::AttrFlag::Computable | tree::AttrFlag::Global => continue,
                         tree::AttrFlag::None => {},
                         tree::AttrFlag::Unique => { 
                             if db.data.contains_key(&(ent_num as u64, i as u64)) {
@@ -164,13 +333,13 @@ impl tree::ComNode {
             }, tree::Command::Delete(ent, lm) => {
                 match db.header.iter().position(|x| x.0 == *ent) {
                     Some(pos) => {
-                        match lm.clone().get_type(&db, &HashMap::new())? {
+                        match lm.get_type(&db, &HashMap::new())? {
                             Type::FuncType(v) if v == vec![Box::new(Type::Object(tree::CoordStr::new(ent.clone()))), 
                                 Box::new(Type::Bool)] => {},
                             q => return Err(format!["({}, {}): incorrect type of lambda in Delete: '{:?}'", self.ln, self.col, q])
                         }
                         let mut deletes = vec![];
-                        for i in 0..db.data[&(0, 0)].len() {
+                        for i in 0..db.row_count(pos) {
                             match compute(&ExprTree::Call(Box::new(Node::simple(ExprTree::LambdaExpr(Box::new(lm.clone()))))
                             , vec![Box::new(Node::simple(ExprTree::Ref(String::new(), String::new(),
                                 Box::new(Node::simple(ExprTree::TupleLit(vec![]))), pos as u64, i as u64)))]), &db, &HashMap::new())? {
@@ -179,6 +348,9 @@ impl tree::ComNode {
                         }
                         deletes.sort();
                         for i in 0..db.header[pos].1.len() {
+                            if let tree::AttrFlag::Computable | tree::AttrFlag::Global = db.header[pos].1[i].flag {
+                                continue;
+                            }
                             let mut index = 0;
                             let mut err = false;
                             db.data.get_mut(&(pos as u64, i as u64)).ok_or("hash table key error")?.retain(|expr| {
@@ -207,17 +379,17 @@ impl tree::ComNode {
                     None => Err(format!["({}, {}): unable to delete from a non-recognized entity '{}'.", self.ln, self.col, ent])
                 }
             }, tree::Command::Eval(expr) => {
-                expr.clone().get_type(&db, &HashMap::new())?;
+                expr.get_type(&db, &HashMap::new())?;
                 let res = compute(&expr.tree, &db, &HashMap::new())?;
                 println!("{}", Node::simple(res).to_string());
                 Ok(db)
             }, tree::Command::Trans(ent, cond, attrs) => {
                 match db.header.iter().position(|x| x.0 == *ent) {
                     Some(pos) => {
-                        if cond.clone().get_type(&db, &HashMap::new())? != Type::FuncType(vec![
+                        if cond.get_type(&db, &HashMap::new())? != Type::FuncType(vec![
                             Box::new(Type::Object(tree::CoordStr::new(ent.clone()))), Box::new(Type::Bool)
                         ]) {return Err(format!["({}, {}): incorrect type of predicate lambda in Transform.", self.ln, self.col])}
-                        for (s, lm) in attrs.clone().as_mut_slice() {
+                        for (s, lm) in attrs.iter_mut() {
                             match db.header[pos].1.iter().position(|x| x.name == *s) {
                                 Some(a) => {
                                     match &db.header[pos].1[a].flag {
@@ -239,11 +411,11 @@ impl tree::ComNode {
                                     s, ent])
                             }
                         }
-                        for i in 0..db.data[&(pos as u64, 0 as u64)].len() {
+                        for i in 0..db.row_count(pos) {
                             match compute(&ExprTree::Call(Box::new(Node::simple(ExprTree::LambdaExpr(Box::new(cond.clone())))),
                                 vec![Box::new(Node::simple(ExprTree::Ref(String::new(), String::new(), Box::new(Node::simple(ExprTree::TupleLit(vec![]))), pos as u64, i as u64)))]), &db, &HashMap::new())? {
                                 ExprTree::BoolLit(true) => {
-                                    for (name, lm) in attrs {
+                                    for (name, lm) in attrs.iter() {
                                         let new_val = compute(&ExprTree::Call(Box::new(Node::simple(ExprTree::LambdaExpr(Box::new(lm.clone())))),
                                             vec![Box::new(Node::simple(ExprTree::Ref(String::new(), String::new(), Box::new(Node::simple(ExprTree::TupleLit(vec![]))), pos as u64, i as u64)))]), &db, &HashMap::new())?;
                                         let attr_pos = db.header[pos].1.iter().position(|x| x.name == *name).unwrap();
@@ -305,6 +477,12 @@ impl tree::ComNode {
                         for i in temp_cont {
                             db.data.insert((i.0, i.1), i.2);
                         }
+                        let new_globals: Vec<_> = db.globals.iter().filter(|(x, _)| x.0 == cur_ent as u64)
+                            .map(|((_, k), v)| (k.clone(), v.clone())).collect();
+                        for (k, v) in new_globals {
+                            add_ref_counts(&mut db, &v.tree);
+                            db.globals.insert(((db.header.len() - 1) as u64, k), v);
+                        }
                         db.header.len() - 1
                     }, None => cur_ent
                 };
@@ -320,7 +498,11 @@ impl tree::ComNode {
                                 return Err(format!["({}, {}): attribute '{}' of entity '{}' already exists and cannot be added in Reshape."
                                     , self.ln, self.col, atr.name, ent]);
                             }
-                            let lm_type = atr.default.clone().as_mut().unwrap().get_type(&db, &HashMap::new())?;
+                            if atr.flag == tree::AttrFlag::Global {
+                                db.header[target].1.push(atr.clone());
+                                continue;
+                            }
+                            let lm_type = atr.default.as_mut().unwrap().get_type(&db, &HashMap::new())?;
                             let mut restrict_lm = None;
                             let needed_type = tree::Type::FuncType(vec![Box::new(tree::Type::Object(tree::CoordStr::new(ent.clone()))),
                                 Box::new(match &atr.attrType {
@@ -332,31 +514,33 @@ impl tree::ComNode {
                                 return Err(format!["({}, {}): default value of attribute '{}' of entity '{}' doesn't match\
                                     its type '{:?}'", self.ln, self.col, atr.name, ent, needed_type]);
                             }
+                            let rows = db.row_count(target);
                             db.header[target].1.push(atr.clone());
+                            if atr.flag == tree::AttrFlag::Computable {
+                                continue;
+                            }
                             let mut newvals: Vec<ExprTree> = vec![];
-                            if let Some(a) = db.data.get(&(target as u64, 0)) {
-                                for i in 0..a.len() {
-                                    let new_value = compute(&ExprTree::Call(Box::new(Node::simple(ExprTree::LambdaExpr(Box::new(atr.default.clone().unwrap())))), 
-                                        vec![Box::new(Node::simple(ExprTree::Ref(String::new(), String::new(),
-                                            Box::new(Node::simple(ExprTree::TupleLit(vec![]))), target as u64, i as u64)))]),&db, &HashMap::new())?;
-                                    if let Some(ref lm) = restrict_lm {
-                                        if let ExprTree::BoolLit(true) = compute(&ExprTree::Call(Box::new(Node::simple(ExprTree::LambdaExpr(Box::new(lm.clone())))),
-                                            vec![Box::new(Node::simple(new_value.clone()))]), &db, &HashMap::new())? {} else {
-                                                return Err(format!["({}, {}): unable to add default values of new attribute '{}' of entity '{}' because they invalidate restriction\
-                                                    of its type Restrict(...).", self.ln, self.col, atr.name, ent]);
-                                            }
-                                    }
-                                    if atr.flag == tree::AttrFlag::Unique {
-                                        for prev in &newvals {
-                                            if let ExprTree::BoolLit(true) = compute(&ExprTree::Eq(true, Box::new(Node::simple(prev.clone())), 
-                                                Box::new(Node::simple(new_value.clone()))), &db, &HashMap::new())? {
-                                                return Err(format!["({}, {}): unable to add default values of new attribute '{}' of entity '{}' because
-                                                    they invalidate attribute's uniqueness.", self.ln, self.col, atr.name, ent]);
-                                            }
+                            for i in 0..rows {
+                                let new_value = compute(&ExprTree::Call(Box::new(Node::simple(ExprTree::LambdaExpr(Box::new(atr.default.clone().unwrap())))), 
+                                    vec![Box::new(Node::simple(ExprTree::Ref(String::new(), String::new(),
+                                        Box::new(Node::simple(ExprTree::TupleLit(vec![]))), target as u64, i as u64)))]),&db, &HashMap::new())?;
+                                if let Some(ref lm) = restrict_lm {
+                                    if let ExprTree::BoolLit(true) = compute(&ExprTree::Call(Box::new(Node::simple(ExprTree::LambdaExpr(Box::new(lm.clone())))),
+                                        vec![Box::new(Node::simple(new_value.clone()))]), &db, &HashMap::new())? {} else {
+                                            return Err(format!["({}, {}): unable to add default values of new attribute '{}' of entity '{}' because they invalidate restriction\
+                                                of its type Restrict(...).", self.ln, self.col, atr.name, ent]);
+                                        }
+                                }
+                                if atr.flag == tree::AttrFlag::Unique {
+                                    for prev in &newvals {
+                                        if let ExprTree::BoolLit(true) = compute(&ExprTree::Eq(true, Box::new(Node::simple(prev.clone())), 
+                                            Box::new(Node::simple(new_value.clone()))), &db, &HashMap::new())? {
+                                            return Err(format!["({}, {}): unable to add default values of new attribute '{}' of entity '{}' because
+                                                they invalidate attribute's uniqueness.", self.ln, self.col, atr.name, ent]);
                                         }
                                     }
-                                    newvals.push(new_value);
                                 }
+                                newvals.push(new_value);
                             }
                             for i in newvals {
                                 match db.data.get_mut(&(target as u64, (db.header[target].1.len() - 1) as u64)) {
@@ -396,11 +580,23 @@ impl tree::ComNode {
                         }
                         false
                     });
+                    if let Some(old) = db.globals.remove(&(target as u64, c as u64)) {
+                        for k in find_refs(&old.tree) {
+                            if db.ref_list[&k] == 1 {
+                                db.ref_list.remove(&k);
+                            } else {
+                                *db.ref_list.get_mut(&k).unwrap() -= 1;
+                            }
+                        }
+                    }
                     for i in c+1..db.header[target].1.len() {
                         if db.data.contains_key(&(target as u64, i as u64)) {
                             let temp = db.data.remove(&(target as u64, i as u64)).unwrap();
                             db.data.insert((target as u64, i as u64 - 1), temp);
                         }
+                        if let Some(temp) = db.globals.remove(&(target as u64, i as u64)) {
+                            db.globals.insert((target as u64, i as u64 - 1), temp);
+                        }
                     }
                     if err {
                         return Err(format!["({}, {}): unable to drop '{}' as it is bound by reference constraint.", self.ln, self.col, ent]);
@@ -408,7 +604,277 @@ impl tree::ComNode {
                     db.header[target].1.remove(c);
                 }
                 Ok(db)
-            }, _ => Err("This command is not supported.".to_string())
+            }, tree::Command::Project(ent, attrs, new_ent) => {
+                let cur_ent = db.header.iter().position(|v| v.0 == *ent).ok_or(
+                    format!["({}, {}): unable to project a non-recognized entity '{}'.", self.ln, self.col, ent])?;
+                if db.header.iter().position(|v| v.0 == *new_ent) != None {
+                    return Err(format!["({}, {}): unable to project into entity '{}' as there already exists an entity with this name.",
+                        self.ln, self.col, new_ent]);
+                }
+                let mut attr_positions = vec![];
+                for atr in attrs {
+                    let atr_pos = db.header[cur_ent].1.iter().position(|x| x.name == *atr).ok_or(
+                        format!["({}, {}): unable to project non-recognized attribute '{}' of entity '{}'.", self.ln, self.col, atr, ent])?;
+                    if db.header[cur_ent].1[atr_pos].flag == tree::AttrFlag::Computable {
+                        return Err(format!["({}, {}): unable to project computable attribute '{}' of entity '{}'.", self.ln, self.col, atr, ent]);
+                    }
+                    if attr_positions.contains(&atr_pos) {
+                        return Err(format!["({}, {}): attribute '{}' of entity '{}' is projected more than once.", self.ln, self.col, atr, ent]);
+                    }
+                    attr_positions.push(atr_pos);
+                }
+                let target = db.header.len() as u64;
+                let new_attrs = attr_positions.iter().map(|x| db.header[cur_ent].1[*x].clone()).collect::<Vec<_>>();
+                for (i, atr) in new_attrs.iter().enumerate() {
+                    if atr.flag == tree::AttrFlag::Global {
+                        if let Some(val) = db.globals.get(&(cur_ent as u64, attr_positions[i] as u64)).cloned() {
+                            add_ref_counts(&mut db, &val.tree);
+                            db.globals.insert((target, i as u64), val);
+                        }
+                        continue;
+                    }
+                    let col = match db.data.get(&(cur_ent as u64, attr_positions[i] as u64)) {
+                        Some(v) => v.clone(),
+                        None => continue
+                    };
+                    if atr.flag == tree::AttrFlag::Unique {
+                        for j in 0..col.len() {
+                            for k in j + 1..col.len() {
+                                if let ExprTree::BoolLit(true) = compute(&ExprTree::Eq(true, Box::new(col[j].clone()),
+                                    Box::new(col[k].clone())), &db, &HashMap::new())? {
+                                    return Err(format!["({}, {}): unable to project unique attribute '{}' of entity '{}' as the projected values \
+                                        are not unique.", self.ln, self.col, atr.name, ent]);
+                                }
+                            }
+                        }
+                    }
+                    for x in &col {
+                        add_ref_counts(&mut db, &x.tree);
+                    }
+                    db.data.insert((target, i as u64), col);
+                }
+                db.header.push((new_ent.clone(), new_attrs));
+                Ok(db)
+            }, tree::Command::Join(ents, lm, new_ent) => {
+                if db.header.iter().position(|v| v.0 == *new_ent) != None {
+                    return Err(format!["({}, {}): unable to join into entity '{}' as there already exists an entity with this name.",
+                        self.ln, self.col, new_ent]);
+                }
+                let mut positions = vec![];
+                let mut needed_type = vec![];
+                for e in ents {
+                    positions.push(db.header.iter().position(|v| v.0 == *e).ok_or(
+                        format!["({}, {}): unable to join a non-recognized entity '{}'.", self.ln, self.col, e])?);
+                    needed_type.push(Box::new(Type::Object(tree::CoordStr::new(e.clone()))));
+                }
+                needed_type.push(Box::new(Type::Bool));
+                match lm.get_type(&db, &HashMap::new())? {
+                    Type::FuncType(v) if v == needed_type => {},
+                    q => return Err(format!["({}, {}): incorrect type of lambda in Join: '{:?}'", self.ln, self.col, q])
+                }
+                let (new_attrs, sources) = combined_header(&db, &positions).map_err(
+                    |x| format!["({}, {}): unable to join entities: {}", self.ln, self.col, x])?;
+                let mut new_cols = vec![vec![]; new_attrs.len()];
+                let sizes: Vec<usize> = positions.iter().map(|x| db.row_count(*x)).collect();
+                let mut rows = vec![0; sizes.len()];
+                let mut more = sizes.iter().all(|s| *s > 0);
+                while more {
+                    let args = rows.iter().enumerate().map(|(i, r)| Box::new(Node::simple(ExprTree::Ref(String::new(), String::new(),
+                        Box::new(Node::simple(ExprTree::TupleLit(vec![]))), positions[i] as u64, *r as u64)))).collect();
+                    match compute(&ExprTree::Call(Box::new(Node::simple(ExprTree::LambdaExpr(Box::new(lm.clone())))), args),
+                        &db, &HashMap::new())? {
+                        ExprTree::BoolLit(true) => {
+                            for (i, (k, a)) in sources.iter().enumerate() {
+                                new_cols[i].push(db.data[&(positions[*k] as u64, *a as u64)][rows[*k]].clone());
+                            }
+                        }, _ => {}
+                    }
+                    more = next_index(&mut rows, &sizes);
+                }
+                let target = db.header.len() as u64;
+                for (i, col) in new_cols.into_iter().enumerate() {
+                    for x in &col {
+                        add_ref_counts(&mut db, &x.tree);
+                    }
+                    db.data.insert((target, i as u64), col);
+                }
+                db.header.push((new_ent.clone(), new_attrs));
+                Ok(db)
+            }, tree::Command::Product(ents, new_ent, limit) => {
+                if db.header.iter().position(|v| v.0 == *new_ent) != None {
+                    return Err(format!["({}, {}): unable to create product entity '{}' as there already exists an entity with this name.",
+                        self.ln, self.col, new_ent]);
+                }
+                let mut positions = vec![];
+                for e in ents {
+                    positions.push(db.header.iter().position(|v| v.0 == *e).ok_or(
+                        format!["({}, {}): unable to take product of a non-recognized entity '{}'.", self.ln, self.col, e])?);
+                }
+                let (new_attrs, sources) = combined_header(&db, &positions).map_err(
+                    |x| format!["({}, {}): unable to take product of entities: {}", self.ln, self.col, x])?;
+                let sizes: Vec<usize> = positions.iter().map(|x| db.row_count(*x)).collect();
+                let mut total: u64 = 1;
+                for s in &sizes {
+                    total = match total.checked_mul(*s as u64) {
+                        Some(v) if v <= *limit => v,
+                        _ => return Err(format!["({}, {}): product entity '{}' would exceed the limit of {} rows.",
+                            self.ln, self.col, new_ent, limit])
+                    };
+                }
+                let mut new_cols = vec![vec![]; new_attrs.len()];
+                let mut rows = vec![0; sizes.len()];
+                let mut more = sizes.iter().all(|s| *s > 0);
+                while more {
+                    for (i, (k, a)) in sources.iter().enumerate() {
+                        new_cols[i].push(db.data[&(positions[*k] as u64, *a as u64)][rows[*k]].clone());
+                    }
+                    more = next_index(&mut rows, &sizes);
+                }
+                let target = db.header.len() as u64;
+                for (i, col) in new_cols.into_iter().enumerate() {
+                    for x in &col {
+                        add_ref_counts(&mut db, &x.tree);
+                    }
+                    db.data.insert((target, i as u64), col);
+                }
+                db.header.push((new_ent.clone(), new_attrs));
+                Ok(db)
+            }, tree::Command::SetOp(op, ent1, ent2, new_ent) => {
+                if db.header.iter().position(|v| v.0 == *new_ent) != None {
+                    return Err(format!["({}, {}): unable to create entity '{}' as there already exists an entity with this name.",
+                        self.ln, self.col, new_ent]);
+                }
+                let mut positions = vec![];
+                for e in [&*ent1, &*ent2] {
+                    positions.push(db.header.iter().position(|v| v.0 == *e).ok_or(
+                        format!["({}, {}): unable to combine a non-recognized entity '{}'.", self.ln, self.col, e])?);
+                }
+                let (new_attrs, columns) = matching_header(&db, positions[0], positions[1]).map_err(
+                    |x| format!["({}, {}): unable to combine entities '{}' and '{}': {}", self.ln, self.col, ent1, ent2, x])?;
+                let rows1 = entity_rows(&db, positions[0], &columns.iter().map(|x| x.0).collect());
+                let rows2 = entity_rows(&db, positions[1], &columns.iter().map(|x| x.1).collect());
+                // Like in SQL, the result contains every row at most once.
+                let mut res: Vec<Vec<Node>> = vec![];
+                let mut res_groups = HashMap::new();
+                let groups2 = group_rows(&rows2);
+                let first_count = rows1.len();
+                let candidates = if *op == tree::SetOperation::Union {[rows1, rows2.clone()].concat()} else {rows1};
+                for (r, row) in candidates.into_iter().enumerate() {
+                    if contains_row(&res, &res_groups, &row, &db)? {
+                        continue;
+                    }
+                    let keep = match op {
+                        tree::SetOperation::Union => true,
+                        tree::SetOperation::Intersect => contains_row(&rows2, &groups2, &row, &db)?,
+                        tree::SetOperation::Minus => !contains_row(&rows2, &groups2, &row, &db)?
+                    };
+                    if !keep {
+                        continue;
+                    }
+                    // Rows of the second entity must also satisfy the restrictions of the first one.
+                    if r >= first_count {
+                        for (atr, val) in new_attrs.iter().zip(row.iter()) {
+                            if let tree::SpType::Restrict(_, p) = &atr.attrType {
+                                match compute(&ExprTree::Call(Box::new(Node::simple(ExprTree::LambdaExpr(Box::new(p.clone())))),
+                                    vec![Box::new(val.clone())]), &db, &HashMap::new())? {
+                                    ExprTree::BoolLit(true) => {},
+                                    _ => return Err(format!["({}, {}): value of attribute '{}' of entity '{}' doesn't satisfy the restriction in '{}'.",
+                                        self.ln, self.col, atr.name, ent2, ent1])
+                                }
+                            }
+                        }
+                    }
+                    res_groups.entry(row.iter().map(|x| value_key(&x.tree)).collect()).or_insert(vec![]).push(res.len());
+                    res.push(row);
+                }
+                for (i, atr) in new_attrs.iter().enumerate() {
+                    if atr.flag == tree::AttrFlag::Unique {
+                        let column = res.iter().map(|r| vec![r[i].clone()]).collect();
+                        for (_, same) in group_rows(&column) {
+                            for (n, j) in same.iter().enumerate() {
+                                for k in &same[n + 1..] {
+                                    if let ExprTree::BoolLit(true) = compute(&ExprTree::Eq(true, Box::new(res[*j][i].clone()),
+                                        Box::new(res[*k][i].clone())), &db, &HashMap::new())? {
+                                        return Err(format!["({}, {}): unable to create entity '{}' as values of unique attribute '{}' would repeat.",
+                                            self.ln, self.col, new_ent, atr.name]);
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+                let target = db.header.len() as u64;
+                for i in 0..new_attrs.len() {
+                    let col: Vec<Node> = res.iter().map(|r| r[i].clone()).collect();
+                    for x in &col {
+                        add_ref_counts(&mut db, &x.tree);
+                    }
+                    db.data.insert((target, i as u64), col);
+                }
+                db.header.push((new_ent.clone(), new_attrs));
+                Ok(db)
+            }, tree::Command::SetGlobal(ent, atr, expr) => {
+                let ent_pos = db.header.iter().position(|v| v.0 == *ent).ok_or(
+                    format!["({}, {}): unable to set a global attribute of a non-recognized entity '{}'.", self.ln, self.col, ent])?;
+                let atr_pos = db.header[ent_pos].1.iter().position(|x| x.name == *atr).ok_or(
+                    format!["({}, {}): entity '{}' doesn't contain the attribute '{}'.", self.ln, self.col, ent, atr])?;
+                if db.header[ent_pos].1[atr_pos].flag != tree::AttrFlag::Global {
+                    return Err(format!["({}, {}): unable to set non-global attribute '{}' of entity '{}'.", self.ln, self.col, atr, ent]);
+                }
+                let val_type = expr.get_type(&db, &HashMap::new())?;
+                let new_val = compute(&expr.tree, &db, &HashMap::new())?;
+                match &db.header[ent_pos].1[atr_pos].attrType {
+                    tree::SpType::Reg(t) => if *t != val_type {
+                        return Err(format!["({}, {}): unable to set a value of type '{:?}' to global attribute '{}' of type '{:?}'.", self.ln, self.col,
+                            val_type, atr, t]);
+                    },
+                    tree::SpType::Gen(_, _, _) => return Err(format!["({}, {}): unable to set values of Gen-type attribute.", self.ln, self.col]),
+                    tree::SpType::Restrict(t, p) => {
+                        if **t != val_type {
+                            return Err(format!["({}, {}): unable to set a value of type '{:?}' to global attribute '{}' of restricted type '{:?}'.", self.ln, self.col,
+                                val_type, atr, t]);
+                        }
+                        match compute(&ExprTree::Call(Box::new(Node::simple(ExprTree::LambdaExpr(Box::new(p.clone())))),
+                            vec![Box::new(Node::simple(new_val.clone()))]), &db, &HashMap::new())? {
+                                ExprTree::BoolLit(true) => {},
+                                _ => return Err(format!["({}, {}): unable to set value not satisfying restriction of global attribute '{}'", self.ln, self.col,
+                                    atr])
+                        }
+                    }
+                }
+                if let Some(old) = db.globals.remove(&(ent_pos as u64, atr_pos as u64)) {
+                    for k in find_refs(&old.tree) {
+                        if db.ref_list[&k] == 1 {
+                            db.ref_list.remove(&k);
+                        } else {
+                            *db.ref_list.get_mut(&k).unwrap() -= 1;
+                        }
+                    }
+                }
+                add_ref_counts(&mut db, &new_val);
+                db.globals.insert((ent_pos as u64, atr_pos as u64), Node::simple(new_val));
+                Ok(db)
+            }, tree::Command::TypeAlias(name, t) => {
+                let reserved = ["Int", "Char", "Bool", "Double", "String", "Func", "Sum", "Maybe", "Gen", "Restrict", "Record", "Object"];
+                if reserved.contains(&name.as_str()) {
+                    return Err(format!["({}, {}): '{}' is a built-in type name.", self.ln, self.col, name]);
+                }
+                if db.types.iter().any(|x| x.0 == *name) {
+                    return Err(format!["({}, {}): the type '{}' already exists.", self.ln, self.col, name]);
+                }
+                let mut t = t.clone();
+                t.check(&db)?;
+                db.types.push((name.clone(), t));
+                Ok(db)
+            }, tree::Command::Def(lm) => {
+                let name = lm.named.clone().unwrap().0;
+                if name == "Std" || db.header.iter().any(|x| x.0 == name) || db.func(&name).is_some() {
+                    return Err(format!["({}, {}): the name '{}' is already in use.", self.ln, self.col, name]);
+                }
+                lm.get_type(&db, &HashMap::new())?;
+                db.funcs.push(lm.clone());
+                Ok(db)
+            }
         }
     }
 }
\ No newline at end of file
@@ -1,5 +1,5 @@
 use std::collections::HashMap;
-use crate::{compute::compute, tree::{self, AttrFlag, DBState, ExprTree, Type, Node}};
+use crate::{compute::compute, stdlib, tree::{self, AttrFlag, DBState, ExprTree, Type, Node}};
 
 impl ToString for Node {
     fn to_string(&self) -> String {
@@ -7,7 +7,10 @@ impl ToString for Node {
             ExprTree::IntLit(n) => n.to_string(),
             ExprTree::BoolLit(n) => n.to_string(),
             ExprTree::CharLit(n) => n.to_string(),
-            ExprTree::ArrayLit(v, _) => {
+            ExprTree::ArrayLit(v, t) => {
+                if v.len() == 0 && *t == Some(Type::Char) {
+                    return "\"\"".to_string();
+                }
                 match v.get(0).map(|x| x.tree.clone()) {
                     Some(ExprTree::CharLit(_)) => {
                         let mut res = String::from("\"");
@@ -38,29 +41,164 @@ impl ToString for Node {
             }, ExprTree::DoubleLit(d) => d.to_string(),
             ExprTree::JustLit(v) => format!["Just({})", v.to_string()],
             ExprTree::NothingLit(_) => "Nothing".to_string(),
+            ExprTree::SumVal(t, i, v) => format!["{}::{}({})", t.to_string(), i, v.to_string()],
+            ExprTree::RecordLit(v) => format!["Record {{{}}}",
+                v.iter().map(|(n, x)| format!["{}: {}", n, x.to_string()]).collect::<Vec<_>>().join(", ")],
             _ => String::new()
         }
     }
 }
 
+// Types are printed in the syntax accepted by 'parse_type'.
+impl ToString for Type {
+    fn to_string(&self) -> String {
+        let list = |v: &Vec<Box<Type>>| v.iter().map(|x| x.to_string()).collect::<Vec<_>>().join(", ");
+        match self {
+            Type::Int => "Int".to_string(),
+            Type::Char => "Char".to_string(),
+            Type::Bool => "Bool".to_string(),
+            Type::Double => "Double".to_string(),
+            Type::Object(e) => format!["Object({})", e.name],
+            Type::FuncType(v) => format!["Func({})", list(v)],
+            Type::Array(t) => format!["[{}]", t.to_string()],
+            Type::Maybe(t) => format!["Maybe({})", t.to_string()],
+            Type::Tuple(v) => format!["({})", list(v)],
+            Type::Sum(v) => format!["Sum({})", list(v)],
+            Type::Record(v) => format!["Record {{{}}}",
+                v.iter().map(|(n, x)| format!["{}: {}", n, x.to_string()]).collect::<Vec<_>>().join(", ")],
+            Type::Alias(n) => n.name.clone(),
+            Type::Var(n) => n.clone(),
+            Type::Forall(_, t) => t.to_string()
+        }
+    }
+}
+
+fn map_vars(t: &Type, f: &dyn Fn(&str) -> Option<Type>) -> Type {
+    let vec_map = |v: &Vec<Box<Type>>| v.iter().map(|x| Box::new(map_vars(x, f))).collect();
+    match t {
+        Type::Var(v) => f(v).unwrap_or(t.clone()),
+        Type::Array(x) => Type::Array(Box::new(map_vars(x, f))),
+        Type::Maybe(x) => Type::Maybe(Box::new(map_vars(x, f))),
+        Type::Tuple(v) => Type::Tuple(vec_map(v)),
+        Type::FuncType(v) => Type::FuncType(vec_map(v)),
+        Type::Sum(v) => Type::Sum(vec_map(v)),
+        Type::Record(v) => Type::Record(v.iter().map(|(n, x)| (n.clone(), Box::new(map_vars(x, f)))).collect()),
+        Type::Forall(vars, x) => Type::Forall(vars.clone(), Box::new(map_vars(x,
+            &|v: &str| if vars.iter().any(|x| x == v) {None} else {f(v)}))),
+        _ => t.clone()
+    }
+}
+fn is_omitted(t: &Type) -> bool {
+    *t == Type::Var("_".to_string())
+}
+fn has_omitted_params(n: &Node) -> bool {
+    match &n.tree {
+        ExprTree::LambdaExpr(lm) => lm.has_omitted(),
+        _ => false
+    }
+}
+fn object_type(ent: &str) -> Type {
+    Type::Object(tree::CoordStr::new(ent.to_string()))
+}
+fn type_vars(t: &Type) -> Vec<String> {
+    let res = std::cell::RefCell::new(vec![]);
+    map_vars(t, &|v: &str| {res.borrow_mut().push(v.to_string()); None});
+    res.into_inner()
+}
+// Replaces the quantified variables by fresh ones, which may be bound during unification.
+fn instantiate(t: &Type, vars: &Vec<String>, fresh: &mut u64) -> Type {
+    *fresh += 1;
+    let n = *fresh;
+    map_vars(t, &|v: &str| if vars.iter().any(|x| x == v) {Some(Type::Var(format!["?{}:{}", n, v]))} else {None})
+}
+fn substitute(t: &Type, subst: &HashMap<String, Type>) -> Type {
+    map_vars(t, &|v: &str| subst.get(v).map(|x| substitute(x, subst)))
+}
+// Variables of the enclosing lambdas are rigid, so only the fresh variables of instantiated types are bound.
+fn unify(a: &Type, b: &Type, subst: &mut HashMap<String, Type>, fresh: &mut u64) -> bool {
+    let (a, b) = (substitute(a, subst), substitute(b, subst));
+    let vec_unify = |x: &Vec<Box<Type>>, y: &Vec<Box<Type>>, subst: &mut HashMap<String, Type>, fresh: &mut u64|
+        x.len() == y.len() && x.iter().zip(y.iter()).all(|(x, y)| unify(x, y, subst, fresh));
+    match (&a, &b) {
+        (Type::Var(x), Type::Var(y)) if x == y => true,
+        (Type::Var(x), t) | (t, Type::Var(x)) if x.starts_with('?') => {
+            if type_vars(t).contains(x) {
+                return false;
+            }
+            subst.insert(x.clone(), t.clone());
+            true
+        },
+        (Type::Forall(vars, t), other) | (other, Type::Forall(vars, t)) => {
+            let inst = instantiate(t, vars, fresh);
+            unify(&inst, other, subst, fresh)
+        },
+        (Type::Array(x), Type::Array(y)) | (Type::Maybe(x), Type::Maybe(y)) => unify(x, y, subst, fresh),
+        (Type::Tuple(x), Type::Tuple(y)) | (Type::FuncType(x), Type::FuncType(y)) | (Type::Sum(x), Type::Sum(y)) =>
+            vec_unify(x, y, subst, fresh),
+        (Type::Record(x), Type::Record(y)) => x.len() == y.len() &&
+            x.iter().zip(y.iter()).all(|((n1, x), (n2, y))| n1 == n2 && unify(x, y, subst, fresh)),
+        _ => a == b
+    }
+}
+
 fn comaparable(t1: &Type, t2: &Type) -> bool {
     match t1 {
-        Type::Object(_) | Type::FuncType(_) | Type::Sum(_) | Type::Double => false,
+        Type::FuncType(_) | Type::Alias(_) | Type::Var(_) | Type::Forall(_, _) => false,
+        Type::Object(ent) => *t2 == Type::Object(ent.clone()),
+        Type::Double => *t2 == Type::Double,
+        Type::Sum(v) => *t2 == Type::Sum(v.clone()) && v.iter().all(|x| comaparable(x, x)),
         Type::Array(t) => *t2 == Type::Array(t.clone()) && comaparable(t, t),
         Type::Bool => *t2 == Type::Bool,
         Type::Int | Type::Char => *t2 == Type::Int || *t2 == Type::Char,
         Type::Tuple(v) => *t2 == Type::Tuple(v.clone()) 
             && v.iter().all(|x| comaparable(x, x)),
+        Type::Record(v) => *t2 == Type::Record(v.clone())
+            && v.iter().all(|(_, x)| comaparable(x, x)),
         Type::Maybe(v) => *t2 == Type::Maybe(v.clone()) && comaparable(v, v)
     }
 }
 
+// Whether values of the two types can be compared by '<' and '>'; composite types are ordered lexicographically.
+pub fn ordered(t1: &Type, t2: &Type) -> bool {
+    match t1 {
+        Type::Int => *t2 == Type::Int || *t2 == Type::Char || *t2 == Type::Double,
+        Type::Char => *t2 == Type::Int || *t2 == Type::Char,
+        Type::Double => *t2 == Type::Int || *t2 == Type::Double,
+        Type::Bool => *t2 == Type::Bool,
+        Type::Array(t) => *t2 == Type::Array(t.clone()) && ordered(t, t),
+        Type::Tuple(v) => *t2 == Type::Tuple(v.clone()) && v.iter().all(|x| ordered(x, x)),
+        Type::Maybe(v) => *t2 == Type::Maybe(v.clone()) && ordered(v, v),
+        _ => false
+    }
+}
+
+// Result type of a lambda applied to each row of the entity.
+fn row_lambda_type(lm: &mut tree::Lambda, ent: &str, db: &DBState, params: &HashMap<String, Type>, ln: u64, col: u64) -> Result<Type, String> {
+    match lm.get_type(db, params)? {
+        Type::FuncType(v) if v.len() == 2 && *v[0] == object_type(ent) => Ok(*v[1].clone()),
+        _ => Err(format!["({}, {}): wrong type of lambda in 'group'.", ln, col])
+    }
+}
+
+fn same_pattern(p1: &tree::Pattern, p2: &tree::Pattern) -> bool {
+    match (p1, p2) {
+        (tree::Pattern::Just(_), tree::Pattern::Just(_)) | (tree::Pattern::Nothing, tree::Pattern::Nothing) |
+            (tree::Pattern::Wildcard, tree::Pattern::Wildcard) => true,
+        (tree::Pattern::Typed(_, t1), tree::Pattern::Typed(_, t2)) => t1 == t2,
+        (tree::Pattern::Variant(i1, _), tree::Pattern::Variant(i2, _)) => i1 == i2,
+        _ => false
+    }
+}
+
 fn get_tree_type(t: &mut Node, db: &DBState, params: &HashMap<String, Type>) -> Result<Type, String> {
     match &mut t.tree {
         ExprTree::IntLit(_) => Ok(Type::Int),
         ExprTree::CharLit(_) => Ok(Type::Char),
         ExprTree::DoubleLit(_) => Ok(Type::Double),
         ExprTree::BoolLit(_) => Ok(Type::Bool),
+        // Computed references, e.g. the rows bound to lambda parameters, only keep the entity position.
+        ExprTree::Ref(ent, _, _, u, _) if ent.is_empty() => db.header.get(*u as usize).map(|x| object_type(&x.0)).ok_or(
+            format!["({}, {}): reference to a removed entity.", t.ln, t.col]),
         ExprTree::Ref(ent, attr, val, u, v) => {
             let ent_pos = db.header.iter().position(|x| x.0 == *ent).ok_or(
                 format!["({}, {}): reference to non-recognized entity '{}'.", t.ln, t.col, ent])?;
@@ -94,6 +232,13 @@ fn get_tree_type(t: &mut Node, db: &DBState, params: &HashMap<String, Type>) ->
             Ok(Type::Object(tree::CoordStr::new(db.header[ent_pos].0.clone())))
         },
         ExprTree::LambdaExpr(lm) => lm.get_type(db, params),
+        ExprTree::Closure(lm, env) => {
+            let mut env_types = HashMap::new();
+            for (n, v) in env {
+                env_types.insert(n.clone(), get_tree_type(v, db, &HashMap::new())?);
+            }
+            lm.get_type(db, &env_types)
+        },
         ExprTree::ArrayLit(t1, opt_type) => {
             if t1.len() == 0 {
                 return Ok(Type::Array(Box::new(opt_type.clone().ok_or(
@@ -112,10 +257,36 @@ fn get_tree_type(t: &mut Node, db: &DBState, params: &HashMap<String, Type>) ->
                 types.push(Box::new(get_tree_type(i, db, params)?));
             }
             Ok(Type::Tuple(types))
+        }, ExprTree::RecordLit(v) => {
+            let mut fields = vec![];
+            for (n, i) in v {
+                fields.push((n.clone(), Box::new(get_tree_type(i, db, params)?)));
+            }
+            Ok(Type::Record(fields))
         }, ExprTree::JustLit(t) => Ok(Type::Maybe(Box::new(get_tree_type(t, db, params)?))),
         ExprTree::NothingLit(t) => Ok(Type::Maybe(Box::new(t.clone()))),
+        ExprTree::SumVal(st, i, v) => {
+            let val_type = get_tree_type(v, db, params)?;
+            match st {
+                Type::Sum(variants) => match variants.get(*i as usize) {
+                    Some(vt) if **vt == val_type => Ok(st.clone()),
+                    Some(vt) => Err(format!["({}, {}): injected value of type '{:?}' doesn't match variant {} of type '{:?}'.",
+                        t.ln, t.col, val_type, i, vt]),
+                    None => Err(format!["({}, {}): variant {} is out of bounds of the sum type.", t.ln, t.col, i])
+                }, _ => Err(format!["({}, {}): injection into a non-sum type.", t.ln, t.col])
+            }
+        },
         ExprTree::Ident(s) => {
             if params.contains_key(s) { return Ok(params[s].clone()); }
+            if let Some(lm) = db.func(s) {
+                // Definitions were checked when they were added, so the signature is enough.
+                let mut v: Vec<_> = lm.params.iter().map(|x| Box::new(x.1.clone())).collect();
+                v.push(Box::new(lm.named.clone().unwrap().1));
+                if lm.tvars.len() != 0 {
+                    return Ok(Type::Forall(lm.tvars.clone(), Box::new(Type::FuncType(v))));
+                }
+                return Ok(Type::FuncType(v));
+            }
             return Err(format!["({}, {}): unrecognized identifier: '{}'", t.ln, t.col, s]);
         }, ExprTree::IfExpr(cond, b1, b2) => {
             let ct = get_tree_type(cond, db, params)?;
@@ -124,7 +295,13 @@ fn get_tree_type(t: &mut Node, db: &DBState, params: &HashMap<String, Type>) ->
                 if t1 == t2 {
                     return Ok(t1);
                 }
-                return Ok(Type::Sum(vec![Box::new(t1), Box::new(t2)]));
+                // Branches of different types are injected into the sum, so the value always carries its variant.
+                let st = Type::Sum(vec![Box::new(t1), Box::new(t2)]);
+                for (i, b) in [b1, b2].iter_mut().enumerate() {
+                    let inner = std::mem::replace(&mut b.tree, ExprTree::TupleLit(vec![]));
+                    b.tree = ExprTree::SumVal(st.clone(), i as u64, Box::new(Node { tree: inner, col: b.col, ln: b.ln }));
+                }
+                return Ok(st);
             }
             return Err(format!["({}, {}): if clause has a non-bool condition.", t.ln, t.col]);
         }, ExprTree::Mod(v1, v2) => {
@@ -166,46 +343,242 @@ fn get_tree_type(t: &mut Node, db: &DBState, params: &HashMap<String, Type>) ->
                 }
             }
             return Err(format!["({}, {}): '{:?}' + '{:?}' is undefined.", t.ln, t.col, t1, t2]);
+        }, ExprTree::And(v1, v2) | ExprTree::Or(v1, v2) => {
+            let (t1, t2) = (get_tree_type(v1, db, params)?, get_tree_type(v2, db, params)?);
+            if t1 == Type::Bool && t2 == Type::Bool {
+                Ok(Type::Bool)
+            } else {Err(format!["({}, {}): boolean connective applied to '{:?}' and '{:?}'.", t.ln, t.col, t1, t2])}
+        }, ExprTree::Not(v) => {
+            let t1 = get_tree_type(v, db, params)?;
+            if t1 == Type::Bool {
+                Ok(Type::Bool)
+            } else {Err(format!["({}, {}): '!' applied to '{:?}'.", t.ln, t.col, t1])}
+        }, ExprTree::Neg(v) => {
+            let t1 = get_tree_type(v, db, params)?;
+            if t1 == Type::Int || t1 == Type::Double {
+                Ok(t1)
+            } else {Err(format!["({}, {}): unary '-' applied to '{:?}'.", t.ln, t.col, t1])}
+        }, ExprTree::Match(val, arms) => {
+            let val_type = get_tree_type(val, db, params)?;
+            let mut covered = vec![];
+            let mut res_type = None;
+            for (pat, body) in arms.iter_mut() {
+                // A type pattern is resolved to the variant it selects, so arms are told apart by index.
+                if let (Type::Sum(v), tree::Pattern::Typed(x, pt)) = (&val_type, &*pat) {
+                    let found: Vec<_> = v.iter().enumerate().filter(|(_, vt)| ***vt == *pt).map(|(i, _)| i).collect();
+                    if found.len() > 1 {
+                        return Err(format!["({}, {}): type '{:?}' names several variants of the matched sum, use an index pattern.",
+                            body.ln, body.col, pt]);
+                    } else if let Some(i) = found.first() {
+                        *pat = tree::Pattern::Variant(*i as u64, x.clone());
+                    }
+                }
+                if covered.iter().any(|x| *x == tree::Pattern::Wildcard || same_pattern(x, pat)) {
+                    return Err(format!["({}, {}): unreachable arm in match.", body.ln, body.col]);
+                }
+                let mut new_params = params.clone();
+                match (&val_type, &*pat) {
+                    (Type::Maybe(inner), tree::Pattern::Just(x)) => { new_params.insert(x.clone(), *inner.clone()); },
+                    (Type::Maybe(_), tree::Pattern::Nothing) | (_, tree::Pattern::Wildcard) => {},
+                    (Type::Sum(v), tree::Pattern::Variant(i, x)) if (*i as usize) < v.len() => {
+                        new_params.insert(x.clone(), *v[*i as usize].clone());
+                    },
+                    _ => return Err(format!["({}, {}): pattern doesn't match the type '{:?}' of matched value.", body.ln, body.col, val_type])
+                }
+                covered.push(pat.clone());
+                let arm_type = get_tree_type(body, db, &new_params)?;
+                match &res_type {
+                    Some(rt) if *rt != arm_type => return Err(format!["({}, {}): arms of match have different types '{:?}' and '{:?}'.",
+                        body.ln, body.col, rt, arm_type]),
+                    _ => res_type = Some(arm_type)
+                }
+            }
+            let exhaustive = covered.contains(&tree::Pattern::Wildcard) || match &val_type {
+                Type::Maybe(_) => covered.contains(&tree::Pattern::Nothing) && covered.iter().any(|x| matches!(x, tree::Pattern::Just(_))),
+                Type::Sum(v) => (0..v.len() as u64).all(|i| covered.iter().any(|x| matches!(x, tree::Pattern::Variant(j, _) if *j == i))),
+                _ => false
+            };
+            if !exhaustive {
+                return Err(format!["({}, {}): non-exhaustive match on type '{:?}'.", t.ln, t.col, val_type]);
+            }
+            res_type.ok_or(format!["({}, {}): match without arms.", t.ln, t.col])
+        }, ExprTree::Index(arr, i) => {
+            let (t1, t2) = (get_tree_type(arr, db, params)?, get_tree_type(i, db, params)?);
+            match t1 {
+                Type::Array(et) if t2 == Type::Int => Ok(Type::Maybe(et)),
+                _ => Err(format!["({}, {}): indexing '{:?}' with '{:?}' is undefined.", t.ln, t.col, t1, t2])
+            }
+        }, ExprTree::Slice(arr, from, to) => {
+            let t1 = get_tree_type(arr, db, params)?;
+            for b in [from, to] {
+                if let Some(b) = b {
+                    let bt = get_tree_type(b, db, params)?;
+                    if bt != Type::Int {
+                        return Err(format!["({}, {}): slice bound of type '{:?}' is not an Int.", t.ln, t.col, bt]);
+                    }
+                }
+            }
+            match t1 {
+                Type::Array(_) => Ok(t1),
+                _ => Err(format!["({}, {}): slicing '{:?}' is undefined.", t.ln, t.col, t1])
+            }
+        }, ExprTree::Let(bindings, body) => {
+            let mut new_params = params.clone();
+            for (name, val) in bindings {
+                let val_type = get_tree_type(val, db, &new_params)?;
+                new_params.insert(name.clone(), val_type);
+            }
+            get_tree_type(body, db, &new_params)
         }, ExprTree::Eq(_, v1, v2) => {
             let (t1, t2) = (get_tree_type(v1, db, params)?, get_tree_type(v2, db, params)?);
             if comaparable(&t1, &t2) {Ok(Type::Bool)} else {Err(format![
                 "({}, {}): types '{:?}' and '{:?}' are noncomparable for equality.", t.ln, t.col, t1, t2])}
         }, ExprTree::Cmp(_, _, v1, v2) => {
             let (t1, t2) = (get_tree_type(v1, db, params)?, get_tree_type(v2, db, params)?);
-            if (t1 == Type::Int || t1 == Type::Char) && (t2 == Type::Int || t2 == Type::Char) {
-                return Ok(Type::Bool);
-            } else if (t1 == Type::Int || t1 == Type::Double) && (t2 == Type::Int || t2 == Type::Double) {
+            if ordered(&t1, &t2) {
                 return Ok(Type::Bool);
             }
             Err(format!["({}, {}): types '{:?}' and '{:?}' are noncomparable.", t.ln, t.col, t1, t2])
-        }, ExprTree::For(ent, lm) => {
-            if db.header.iter().position(|(x, _)| *x == *ent) == None {
-                return Err(format!["({}, {}): reference to non-recognized entity in 'for'.", t.ln, t.col]);
+        }, ExprTree::For(ents, lm, clauses) => {
+            for ent in ents.iter() {
+                if !db.header.iter().any(|(x, _)| x == ent) {
+                    return Err(format!["({}, {}): reference to non-recognized entity '{}' in 'for'.", t.ln, t.col, ent]);
+                }
+            }
+            let objs: Vec<_> = ents.iter().map(|x| Box::new(object_type(x))).collect();
+            let n = ents.len();
+            if let Some((key, _)) = &mut clauses.order {
+                let key_type = match key.get_type(db, params)? {
+                    Type::FuncType(v) if v.len() == n + 1 && v[..n] == objs[..] => *v[n].clone(),
+                    _ => return Err(format!["({}, {}): wrong type of sort key lambda in 'for'.", t.ln, t.col])
+                };
+                if !ordered(&key_type, &key_type) {
+                    return Err(format!["({}, {}): sort key of type '{:?}' is not ordered.", t.ln, t.col, key_type]);
+                }
+            }
+            for val in [&mut clauses.limit, &mut clauses.offset] {
+                if let Some(v) = val {
+                    if get_tree_type(v, db, params)? != Type::Int {
+                        return Err(format!["({}, {}): limit and offset of 'for' must be of type 'Int'.", v.ln, v.col]);
+                    }
+                }
             }
             let lm_type = lm.get_type(db, params)?;
             match lm_type {
-                Type::FuncType(v) => {
-                    if let Type::Object(obj) = *v.get(0).ok_or(format!["({}, {}): wrong type of lambda in 'for'.", t.ln, t.col])?.clone() {
-                        if obj.name == ent.clone() {
-                            match **v.get(1).ok_or(format!["({}, {}): wrong type of lambda in 'for'.", t.ln, t.col])? {
-                                Type::Maybe(ref t) => {
-                                    return Ok(Type::Array(t.clone()));
-                                }, _ => {}
-                            }
-                        }
+                Type::FuncType(v) if v.len() == n + 1 && v[..n] == objs[..] => {
+                    match *v[n] {
+                        Type::Maybe(ref t) => {
+                            return Ok(Type::Array(t.clone()));
+                        }, _ => {}
                     }
                 }, _ => {}
             }
             return Err(format!["({}, {}): wrong type of lambda in 'for'.", t.ln, t.col]);
-        }, ExprTree::Call(f, args) => {
-            match get_tree_type(f, db, params)? {
-                Type::FuncType(v) => {
+        }, ExprTree::Group(ent, key, aggs) => {
+            if !db.header.iter().any(|(x, _)| *x == *ent) {
+                return Err(format!["({}, {}): reference to non-recognized entity in 'group'.", t.ln, t.col]);
+            }
+            let key_type = row_lambda_type(key, ent, db, params, t.ln, t.col)?;
+            if !comaparable(&key_type, &key_type) {
+                return Err(format!["({}, {}): group key of type '{:?}' is noncomparable for equality.", t.ln, t.col, key_type]);
+            }
+            let mut fields = vec![("key".to_string(), Box::new(key_type))];
+            for (name, agg, arg) in aggs.iter_mut() {
+                let field_type = match arg {
+                    None => Type::Int,
+                    Some(lm) => {
+                        let arg_type = row_lambda_type(lm, ent, db, params, t.ln, t.col)?;
+                        match agg.as_str() {
+                            "sum" if arg_type == Type::Int || arg_type == Type::Double => arg_type,
+                            "avg" if arg_type == Type::Int || arg_type == Type::Double => Type::Double,
+                            "min" | "max" if ordered(&arg_type, &arg_type) => arg_type,
+                            _ => return Err(format!["({}, {}): aggregate '{}' cannot be applied to type '{:?}'.", t.ln, t.col, agg, arg_type])
+                        }
+                    }
+                };
+                fields.push((name.clone(), Box::new(field_type)));
+            }
+            fields.sort_by(|x, y| x.0.cmp(&y.0));
+            Ok(Type::Array(Box::new(Type::Record(fields))))
+        }, ExprTree::StdFunc(n) => stdlib::value_type(n).ok_or(
+            format!["({}, {}): standard function 'Std.{}' cannot be used as a value.", t.ln, t.col, n]),
+        ExprTree::Call(f, args) => {
+            if let ExprTree::Dot(v1, v2) = &f.tree {
+                if let (ExprTree::Ident(s1), ExprTree::Ident(s2)) = (&v1.tree, &v2.tree) {
+                    if s1 == "Std" && !db.header.iter().any(|(x, _)| x == "Std") && !params.contains_key("Std") {
+                        // Lambda arguments with omitted parameter types are typed after the other arguments.
+                        let mut arg_types = vec![None; args.len()];
+                        for (i, x) in args.iter_mut().enumerate() {
+                            if !has_omitted_params(x) {
+                                arg_types[i] = Some(get_tree_type(x, db, params)?);
+                            }
+                        }
+                        for i in 0..args.len() {
+                            if let ExprTree::LambdaExpr(lm) = &mut args[i].tree {
+                                if lm.has_omitted() {
+                                    if let Some(v) = stdlib::lambda_param_types(s2, i, &arg_types) {
+                                        lm.infer_params(&v);
+                                    }
+                                    arg_types[i] = Some(get_tree_type(&mut args[i], db, params)?);
+                                }
+                            }
+                        }
+                        return stdlib::call_type(s2, &arg_types.into_iter().map(|x| x.unwrap()).collect())
+                            .map_err(|e| format!["({}, {}): {}", t.ln, t.col, e]);
+                    }
+                }
+            }
+            // A lambda called directly takes the omitted parameter types from the arguments.
+            if let ExprTree::LambdaExpr(lm) = &mut f.tree {
+                if lm.has_omitted() {
                     let mut arg_types = vec![];
-                    for x in args {
-                        arg_types.push(Box::new(get_tree_type(x, db, params)?));
+                    for x in args.iter_mut() {
+                        arg_types.push(get_tree_type(x, db, params)?);
+                    }
+                    lm.infer_params(&arg_types);
+                }
+            }
+            let (mut subst, mut fresh) = (HashMap::new(), 0);
+            let f_type = match get_tree_type(f, db, params)? {
+                Type::Forall(vars, ft) => instantiate(&ft, &vars, &mut fresh),
+                ft => ft
+            };
+            match f_type {
+                Type::FuncType(v) => {
+                    if v.len() - 1 != args.len() {
+                        return Err(format!["({}, {}): parameter type mismatch in call.", t.ln, t.col]);
+                    }
+                    let mut deferred = vec![];
+                    let mut ok = true;
+                    for (i, x) in args.iter_mut().enumerate() {
+                        if has_omitted_params(x) {
+                            deferred.push(i);
+                            continue;
+                        }
+                        let a = get_tree_type(x, db, params)?;
+                        ok = ok && unify(&v[i], &a, &mut subst, &mut fresh);
+                    }
+                    // Omitted parameter types of lambda arguments come from the instantiated parameter type.
+                    for i in deferred {
+                        if let (ExprTree::LambdaExpr(lm), Type::FuncType(pv)) = (&mut args[i].tree, substitute(&v[i], &subst)) {
+                            if pv.len() - 1 == lm.params.len() {
+                                for (j, (n, pt)) in lm.params.iter().enumerate() {
+                                    if is_omitted(pt) && type_vars(&pv[j]).iter().any(|x| x.starts_with('?')) {
+                                        return Err(format!["({}, {}): the type of parameter '{}' of the lambda argument is ambiguous.", t.ln, t.col, n]);
+                                    }
+                                }
+                                lm.infer_params(&pv[0..pv.len() - 1].iter().map(|x| *x.clone()).collect());
+                            }
+                        }
+                        let a = get_tree_type(&mut args[i], db, params)?;
+                        ok = ok && unify(&v[i], &a, &mut subst, &mut fresh);
                     }
-                    if v[0..v.len() - 1] == arg_types {
-                        return Ok(*v[0].clone());
+                    if ok {
+                        let res = substitute(&v[v.len() - 1], &subst);
+                        if type_vars(&res).iter().any(|x| x.starts_with('?')) {
+                            return Err(format!["({}, {}): unable to infer the result type of the call.", t.ln, t.col]);
+                        }
+                        return Ok(res);
                     }
                     Err(format!["({}, {}): parameter type mismatch in call.", t.ln, t.col])
                 }, _ => Err(format!["({}, {}): non-function type cannot be called.", t.ln, t.col])
@@ -214,10 +587,13 @@ fn get_tree_type(t: &mut Node, db: &DBState, params: &HashMap<String, Type>) ->
             match v2.tree {
                 ExprTree::Ident(ref s2) => {
                     if let ExprTree::Ident(ref s1) = v1.tree {
-                        // if s1 == "Std" {
-                        //     // std functions go here.
-                        //     return Err(format!["({}, {}): unrecognized standard identifier '{}'", t.ln, t.col, s2])
-                        // } else {      
+                        if s1 == "Std" && !db.header.iter().any(|(x, _)| x == "Std") && !params.contains_key("Std") {
+                            if !stdlib::exists(s2) {
+                                return Err(format!["({}, {}): unrecognized standard identifier '{}'", t.ln, t.col, s2]);
+                            }
+                            return stdlib::value_type(s2).ok_or(
+                                format!["({}, {}): polymorphic standard function 'Std.{}' can only be called directly.", t.ln, t.col, s2]);
+                        } else {
                             if let Some((_, ent)) = db.header.iter().find(|(x, _)| *x == *s1) {
                                 if let Some(a) = ent.iter().find(|x| x.name == *s2) {
                                     if let AttrFlag::Global = a.flag {
@@ -228,10 +604,17 @@ fn get_tree_type(t: &mut Node, db: &DBState, params: &HashMap<String, Type>) ->
                                         };
                                     }
                                 }
-                            // }
+                            }
                         }
                     }
-                    if let Type::Object(ent) = get_tree_type(v1, db, params)? {
+                    let t1 = get_tree_type(v1, db, params)?;
+                    if let Type::Record(fields) = &t1 {
+                        return match fields.iter().find(|(n, _)| n == s2) {
+                            Some((_, ft)) => Ok(*ft.clone()),
+                            None => Err(format!["({}, {}): record has no field '{}'.", t.ln, t.col, s2])
+                        };
+                    }
+                    if let Type::Object(ent) = t1 {
                         if let Some((_, ent_v)) = db.header.iter().find(|(x, _)| *x == ent.name) {
                             if let Some(a) = ent_v.iter().find(|x| x.name == *s2) {
                                 return match &a.attrType {
@@ -244,6 +627,15 @@ fn get_tree_type(t: &mut Node, db: &DBState, params: &HashMap<String, Type>) ->
                         return Err(format!["({}, {}): unrecognized or incorrect entity reference of attribute '{}' of entity '{}'.", t.ln, t.col, s2, ent.name]);
                     }
                     Err(format!["({}, {}): '.' applied to non-identifier.", t.ln, t.col])
+                }, ExprTree::IntLit(i) => {
+                    match get_tree_type(v1, db, params)? {
+                        Type::Tuple(v) => {
+                            if i < 0 || i as usize >= v.len() {
+                                return Err(format!["({}, {}): index {} is out of bounds of tuple of arity {}.", t.ln, t.col, i, v.len()]);
+                            }
+                            Ok(*v[i as usize].clone())
+                        }, t1 => Err(format!["({}, {}): projection '.{}' applied to non-tuple type '{:?}'.", t.ln, t.col, i, t1])
+                    }
                 }, _ => Err(format!["({}, {}): '.' applied to non-identifier.", t.ln, t.col])
             }
         }
@@ -260,6 +652,9 @@ impl tree::Lambda {
     pub fn get_type(&mut self, db: &DBState, params: &HashMap<String, Type>) -> Result<Type, String> {
         let mut params_ht = params.clone();
         let mut param_types = Vec::new();
+        if let Some((n, _)) = self.params.iter().find(|x| is_omitted(&x.1)) {
+            return Err(format!["({}, {}): unable to infer the type of parameter '{}', it needs an annotation.", self.code.ln, self.code.col, n]);
+        }
         for i in &self.params {
             tree::SpType::Reg(i.1.clone()).check(db)?;
             param_types.push(Box::new(i.1.clone()));
@@ -279,13 +674,18 @@ impl tree::Lambda {
                 return Err(format!["({}, {}): type of lambda doesn't equal specified type", self.code.ln, self.code.col])
             }
         }
+        self.ret = Some(code_type.clone());
         param_types.push(Box::new(code_type));
+        if self.tvars.len() != 0 {
+            return Ok(Type::Forall(self.tvars.clone(), Box::new(Type::FuncType(param_types))));
+        }
         Ok(Type::FuncType(param_types))
     }
 }
 
 impl tree::SpType {
     pub fn check(&mut self, db: &DBState) -> Result<(), String> {
+        self.resolve(db)?;
         match self {
             tree::SpType::Gen(t, func, _) => {
                 tree::SpType::Reg(*t.clone()).check(db)?;
@@ -305,12 +705,226 @@ impl tree::SpType {
                         for t in v {
                             tree::SpType::Reg(*t.clone()).check(db)?;
                         },
+                    tree::Type::Record(v) => for (_, t) in v {
+                        tree::SpType::Reg(*t.clone()).check(db)?;
+                    },
                     tree::Type::Object(s) => if let None = db.header.iter().find(|x| x.0 == *s.name) {
                         return Err(format!["({}, {}): object type refers to a non-recognized entity.", s.ln, s.col]);
-                    }
+                    },
+                    tree::Type::Alias(s) => return Err(format!["({}, {}): unrecognized type '{}'.", s.ln, s.col, s.name]),
+                    tree::Type::Forall(_, t) => tree::SpType::Reg(*t.clone()).check(db)?,
+                    tree::Type::Var(_) => {}
                 }
             }
         }
         Ok(())
     }
-}
\ No newline at end of file
+    // Replaces type aliases with the types they stand for.
+    pub fn resolve(&mut self, db: &DBState) -> Result<(), String> {
+        let tvars = vec![];
+        match self {
+            tree::SpType::Reg(Type::Alias(s)) => match db.types.iter().find(|x| x.0 == s.name) {
+                Some((_, t)) => *self = t.clone(),
+                None => return Err(format!["({}, {}): unrecognized type '{}'.", s.ln, s.col, s.name])
+            },
+            tree::SpType::Reg(t) => t.resolve(db, &tvars)?,
+            tree::SpType::Gen(t, func, _) => {
+                t.resolve(db, &tvars)?;
+                func.resolve(db, &tvars)?;
+                func.infer_params(&vec![Type::Int]);
+            }, tree::SpType::Restrict(t, func) => {
+                t.resolve(db, &tvars)?;
+                func.resolve(db, &tvars)?;
+                func.infer_params(&vec![*t.clone()]);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl tree::Type {
+    // Names in 'tvars' are type variables of the enclosing lambdas and shadow aliases.
+    pub fn resolve(&mut self, db: &DBState, tvars: &Vec<String>) -> Result<(), String> {
+        match self {
+            Type::Alias(s) if tvars.contains(&s.name) => *self = Type::Var(s.name.clone()),
+            Type::Alias(s) => match db.types.iter().find(|x| x.0 == s.name) {
+                Some((_, tree::SpType::Reg(t))) => *self = t.clone(),
+                Some(_) => return Err(format!["({}, {}): type '{}' is a Gen or Restrict type and is only allowed as an attribute type.", s.ln, s.col, s.name]),
+                None => return Err(format!["({}, {}): unrecognized type '{}'.", s.ln, s.col, s.name])
+            },
+            Type::Array(t) | Type::Maybe(t) | Type::Forall(_, t) => t.resolve(db, tvars)?,
+            Type::Tuple(v) | Type::FuncType(v) | Type::Sum(v) => for t in v {
+                t.resolve(db, tvars)?;
+            },
+            Type::Record(v) => for (_, t) in v {
+                t.resolve(db, tvars)?;
+            },
+            Type::Bool | Type::Char | Type::Double | Type::Int | Type::Object(_) | Type::Var(_) => {}
+        }
+        Ok(())
+    }
+}
+
+impl tree::Lambda {
+    pub fn resolve(&mut self, db: &DBState, tvars: &Vec<String>) -> Result<(), String> {
+        let tvars = [tvars.clone(), self.tvars.clone()].concat();
+        for (_, t) in &mut self.params {
+            t.resolve(db, &tvars)?;
+        }
+        if let Some((_, t)) = &mut self.named {
+            t.resolve(db, &tvars)?;
+        }
+        self.code.resolve(db, &tvars)
+    }
+    // Fills the omitted parameter types from the types the context passes.
+    pub fn infer_params(&mut self, types: &Vec<Type>) {
+        for (i, (_, t)) in self.params.iter_mut().enumerate() {
+            if is_omitted(t) && i < types.len() {
+                *t = types[i].clone();
+            }
+        }
+    }
+    pub fn has_omitted(&self) -> bool {
+        self.params.iter().any(|x| is_omitted(&x.1))
+    }
+}
+
+impl tree::Node {
+    pub fn resolve(&mut self, db: &DBState, tvars: &Vec<String>) -> Result<(), String> {
+        match &mut self.tree {
+            ExprTree::ArrayLit(v, t) => {
+                if let Some(t) = t {
+                    t.resolve(db, tvars)?;
+                }
+                for i in v {
+                    i.resolve(db, tvars)?;
+                }
+            }, ExprTree::NothingLit(t) => t.resolve(db, tvars)?,
+            ExprTree::SumVal(t, _, v) => {
+                t.resolve(db, tvars)?;
+                v.resolve(db, tvars)?;
+            }, ExprTree::For(ents, lm, clauses) => {
+                let objs = ents.iter().map(|x| object_type(x)).collect();
+                lm.resolve(db, tvars)?;
+                lm.infer_params(&objs);
+                if let Some((key, _)) = &mut clauses.order {
+                    key.resolve(db, tvars)?;
+                    key.infer_params(&objs);
+                }
+                for val in [&mut clauses.limit, &mut clauses.offset] {
+                    if let Some(v) = val {
+                        v.resolve(db, tvars)?;
+                    }
+                }
+            }, ExprTree::Group(ent, key, aggs) => {
+                for lm in std::iter::once(key).chain(aggs.iter_mut().filter_map(|x| x.2.as_mut())) {
+                    lm.resolve(db, tvars)?;
+                    lm.infer_params(&vec![object_type(ent)]);
+                }
+            },
+            ExprTree::LambdaExpr(lm) => lm.resolve(db, tvars)?,
+            ExprTree::Closure(lm, env) => {
+                lm.resolve(db, tvars)?;
+                for (_, v) in env {
+                    v.resolve(db, tvars)?;
+                }
+            },
+            ExprTree::Ref(_, _, v, _, _) | ExprTree::JustLit(v) | ExprTree::Not(v) | ExprTree::Neg(v) => v.resolve(db, tvars)?,
+            ExprTree::TupleLit(v) => for i in v {
+                i.resolve(db, tvars)?;
+            },
+            ExprTree::RecordLit(v) => for (_, i) in v {
+                i.resolve(db, tvars)?;
+            },
+            ExprTree::Plus(u, v) | ExprTree::Minus(u, v) | ExprTree::Mul(u, v) | ExprTree::Div(u, v) |
+                ExprTree::Mod(u, v) | ExprTree::Exp(u, v) | ExprTree::Dot(u, v) | ExprTree::Eq(_, u, v) |
+                ExprTree::Cmp(_, _, u, v) | ExprTree::And(u, v) | ExprTree::Or(u, v) | ExprTree::Index(u, v) => {
+                u.resolve(db, tvars)?;
+                v.resolve(db, tvars)?;
+            }, ExprTree::Call(f, args) => {
+                f.resolve(db, tvars)?;
+                for i in args {
+                    i.resolve(db, tvars)?;
+                }
+            }, ExprTree::IfExpr(a, b, c) => {
+                a.resolve(db, tvars)?;
+                b.resolve(db, tvars)?;
+                c.resolve(db, tvars)?;
+            }, ExprTree::Let(binds, body) => {
+                for (_, i) in binds {
+                    i.resolve(db, tvars)?;
+                }
+                body.resolve(db, tvars)?;
+            }, ExprTree::Match(e, arms) => {
+                e.resolve(db, tvars)?;
+                for (p, i) in arms {
+                    if let tree::Pattern::Typed(_, t) = p {
+                        t.resolve(db, tvars)?;
+                    }
+                    i.resolve(db, tvars)?;
+                }
+            }, ExprTree::Slice(a, from, to) => {
+                a.resolve(db, tvars)?;
+                for b in [from, to] {
+                    if let Some(b) = b {
+                        b.resolve(db, tvars)?;
+                    }
+                }
+            }, ExprTree::IntLit(_) | ExprTree::CharLit(_) | ExprTree::BoolLit(_) | ExprTree::DoubleLit(_) |
+                ExprTree::Ident(_) | ExprTree::StdFunc(_) => {}
+        }
+        Ok(())
+    }
+}
+
+impl tree::Attr {
+    // The defining lambda of the attribute receives an object of the entity 'ent'.
+    pub fn resolve(&mut self, db: &DBState, ent: &str) -> Result<(), String> {
+        self.attrType.resolve(db)?;
+        if let Some(lm) = &mut self.default {
+            lm.resolve(db, &vec![])?;
+            lm.infer_params(&vec![object_type(ent)]);
+        }
+        Ok(())
+    }
+}
+
+impl tree::Command {
+    // Also infers the omitted parameter types of lambdas whose arguments are fixed by the command.
+    pub fn resolve(&mut self, db: &DBState) -> Result<(), String> {
+        match self {
+            tree::Command::NewEntity(name, attrs) => for a in attrs {
+                a.resolve(db, name)?;
+            },
+            tree::Command::Eval(e) | tree::Command::SetGlobal(_, _, e) => e.resolve(db, &vec![])?,
+            tree::Command::Add(_, vals) => for (_, v) in vals {
+                for e in v {
+                    e.resolve(db, &vec![])?;
+                }
+            },
+            tree::Command::Delete(ent, lm) => {
+                lm.resolve(db, &vec![])?;
+                lm.infer_params(&vec![object_type(ent)]);
+            }, tree::Command::Join(ents, lm, _) => {
+                lm.resolve(db, &vec![])?;
+                lm.infer_params(&ents.iter().map(|x| object_type(x)).collect());
+            }, tree::Command::Def(lm) => lm.resolve(db, &vec![])?,
+            tree::Command::Trans(ent, cond, attrs) => {
+                cond.resolve(db, &vec![])?;
+                cond.infer_params(&vec![object_type(ent)]);
+                for (_, lm) in attrs {
+                    lm.resolve(db, &vec![])?;
+                    lm.infer_params(&vec![object_type(ent)]);
+                }
+            }, tree::Command::Reshape(ent, opts, _) => for o in opts {
+                if let tree::ReshapeOptions::New(a) = o {
+                    a.resolve(db, ent)?;
+                }
+            },
+            tree::Command::TypeAlias(_, t) => t.resolve(db)?,
+            tree::Command::Open(_) | tree::Command::Commit(_) | tree::Command::Project(_, _, _) |
+                tree::Command::Product(_, _, _) | tree::Command::Drop(_) | tree::Command::SetOp(_, _, _, _) => {}
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,197 @@
+use std::collections::HashMap;
+use crate::{compute::{apply, compute, elem_type}, semantic::ordered, tree::{DBState, ExprTree, Node, Type}};
+
+fn string_type() -> Type {
+    Type::Array(Box::new(Type::Char))
+}
+fn as_string(e: &ExprTree) -> Option<String> {
+    match e {
+        ExprTree::ArrayLit(v, _) => v.iter().map(|x| match x.tree {
+            ExprTree::CharLit(c) => Some(c),
+            _ => None
+        }).collect(),
+        _ => None
+    }
+}
+fn string_value(s: &str) -> ExprTree {
+    ExprTree::ArrayLit(s.chars().map(|c| Box::new(Node::simple(ExprTree::CharLit(c)))).collect(), Some(Type::Char))
+}
+// Result type of a function value, known once the lambda was type checked.
+fn result_type(f: &ExprTree) -> Option<Type> {
+    match f {
+        ExprTree::LambdaExpr(lm) | ExprTree::Closure(lm, _) => lm.ret.clone(),
+        ExprTree::StdFunc(n) => match value_type(n) {
+            Some(Type::FuncType(v)) => v.last().map(|x| *x.clone()),
+            _ => None
+        },
+        _ => None
+    }
+}
+fn is_numeric(t: &Type) -> bool {
+    *t == Type::Int || *t == Type::Double
+}
+
+// Type of a standard function used as a value; only monomorphic functions can be used this way.
+pub fn value_type(name: &str) -> Option<Type> {
+    let f = |v: Vec<Type>| Some(Type::FuncType(v.into_iter().map(Box::new).collect()));
+    match name {
+        "toDouble" => f(vec![Type::Int, Type::Double]),
+        "floor" | "ceil" | "round" => f(vec![Type::Double, Type::Int]),
+        "sqrt" => f(vec![Type::Double, Type::Double]),
+        "range" => f(vec![Type::Int, Type::Int, Type::Array(Box::new(Type::Int))]),
+        "substring" => f(vec![string_type(), Type::Int, Type::Int, string_type()]),
+        "toUpper" | "toLower" => f(vec![string_type(), string_type()]),
+        "contains" | "startsWith" | "endsWith" => f(vec![string_type(), string_type(), Type::Bool]),
+        "split" => f(vec![string_type(), string_type(), Type::Array(Box::new(string_type()))]),
+        "parseInt" => f(vec![string_type(), Type::Maybe(Box::new(Type::Int))]),
+        "parseDouble" => f(vec![string_type(), Type::Maybe(Box::new(Type::Double))]),
+        _ => None
+    }
+}
+
+pub fn exists(name: &str) -> bool {
+    match name {
+        "length" | "map" | "filter" | "fold" | "sum" | "min" | "max" | "fromMaybe" | "isJust" |
+            "abs" | "reverse" | "concat" => true,
+        _ => value_type(name).is_some()
+    }
+}
+
+// Types of the parameters of a lambda passed as the argument 'index', given the types of the other arguments.
+pub fn lambda_param_types(name: &str, index: usize, args: &Vec<Option<Type>>) -> Option<Vec<Type>> {
+    match (name, index, args.as_slice()) {
+        ("map" | "filter", 0, [_, Some(Type::Array(t))]) => Some(vec![*t.clone()]),
+        ("fold", 0, [_, Some(acc), Some(Type::Array(t))]) => Some(vec![acc.clone(), *t.clone()]),
+        _ => None
+    }
+}
+
+pub fn call_type(name: &str, args: &Vec<Type>) -> Result<Type, String> {
+    if let Some(Type::FuncType(v)) = value_type(name) {
+        if v[0..v.len() - 1].iter().map(|x| *x.clone()).collect::<Vec<_>>() == *args {
+            return Ok(*v[v.len() - 1].clone());
+        }
+        return Err(format!["parameter type mismatch in call of 'Std.{}'.", name]);
+    }
+    let res = match (name, args.as_slice()) {
+        ("length", [Type::Array(_)]) => Some(Type::Int),
+        ("reverse", [Type::Array(t)]) => Some(Type::Array(t.clone())),
+        ("concat", [Type::Array(t), Type::Array(t2)]) if t == t2 => Some(Type::Array(t.clone())),
+        ("map", [Type::FuncType(f), Type::Array(t)]) if f.len() == 2 && f[0] == *t => Some(Type::Array(f[1].clone())),
+        ("filter", [Type::FuncType(f), Type::Array(t)]) if f.len() == 2 && f[0] == *t && *f[1] == Type::Bool =>
+            Some(Type::Array(t.clone())),
+        ("fold", [Type::FuncType(f), acc, Type::Array(t)]) if f.len() == 3 && *f[0] == *acc && f[1] == *t && *f[2] == *acc =>
+            Some(acc.clone()),
+        ("sum", [Type::Array(t)]) if is_numeric(t) => Some(*t.clone()),
+        ("min" | "max", [Type::Array(t)]) if ordered(t, t) => Some(Type::Maybe(t.clone())),
+        ("fromMaybe", [t, Type::Maybe(t2)]) if **t2 == *t => Some(t.clone()),
+        ("isJust", [Type::Maybe(_)]) => Some(Type::Bool),
+        ("abs", [t]) if is_numeric(t) => Some(t.clone()),
+        _ => None
+    };
+    res.ok_or(if exists(name) {format!["parameter type mismatch in call of 'Std.{}'.", name]}
+        else {format!["unrecognized standard function 'Std.{}'.", name]})
+}
+
+pub fn call(name: &str, args: Vec<ExprTree>, db: &DBState, params: &HashMap<String, ExprTree>) -> Result<ExprTree, String> {
+    let node = |e: ExprTree| Box::new(Node::simple(e));
+    match (name, args.as_slice()) {
+        ("length", [ExprTree::ArrayLit(v, _)]) => Ok(ExprTree::IntLit(v.len() as i64)),
+        ("reverse", [ExprTree::ArrayLit(v, t)]) => Ok(ExprTree::ArrayLit(v.iter().rev().cloned().collect(), t.clone())),
+        ("map", [f, ExprTree::ArrayLit(v, _)]) => {
+            let mut res = vec![];
+            for x in v {
+                res.push(node(apply(f, vec![x.tree.clone()], db, params)?));
+            }
+            Ok(ExprTree::ArrayLit(res, result_type(f)))
+        }, ("filter", [f, ExprTree::ArrayLit(v, t)]) => {
+            let mut res = vec![];
+            for x in v {
+                if let ExprTree::BoolLit(true) = apply(f, vec![x.tree.clone()], db, params)? {
+                    res.push(x.clone());
+                }
+            }
+            let t = if res.is_empty() {Some(elem_type(v, t, db)?)} else {t.clone()};
+            Ok(ExprTree::ArrayLit(res, t))
+        }, ("fold", [f, acc, ExprTree::ArrayLit(v, _)]) => {
+            let mut res = acc.clone();
+            for x in v {
+                res = apply(f, vec![res, x.tree.clone()], db, params)?;
+            }
+            Ok(res)
+        }, ("sum", [ExprTree::ArrayLit(v, t)]) => {
+            let mut res = if elem_type(v, t, db)? == Type::Double {ExprTree::DoubleLit(0.0)} else {ExprTree::IntLit(0)};
+            for (i, x) in v.iter().enumerate() {
+                res = if i == 0 {x.tree.clone()} else {compute(&ExprTree::Plus(node(res), x.clone()), db, params)?};
+            }
+            Ok(res)
+        }, ("min" | "max", [ExprTree::ArrayLit(v, t)]) => {
+            let mut res: Option<ExprTree> = None;
+            for x in v {
+                res = match res {
+                    None => Some(x.tree.clone()),
+                    Some(cur) => match compute(&ExprTree::Cmp(name == "max", false, x.clone(), node(cur.clone())), db, params)? {
+                        ExprTree::BoolLit(true) => Some(x.tree.clone()),
+                        _ => Some(cur)
+                    }
+                };
+            }
+            Ok(match res {
+                Some(r) => ExprTree::JustLit(node(r)),
+                None => ExprTree::NothingLit(elem_type(v, t, db)?)
+            })
+        }, ("fromMaybe", [d, m]) => Ok(match m {
+            ExprTree::JustLit(v) => v.tree.clone(),
+            _ => d.clone()
+        }), ("isJust", [m]) => Ok(ExprTree::BoolLit(matches!(m, ExprTree::JustLit(_)))),
+        ("abs", [ExprTree::IntLit(v)]) => Ok(ExprTree::IntLit(v.abs())),
+        ("abs", [ExprTree::DoubleLit(v)]) => Ok(ExprTree::DoubleLit(v.abs())),
+        ("toDouble", [ExprTree::IntLit(v)]) => Ok(ExprTree::DoubleLit(*v as f64)),
+        ("floor", [ExprTree::DoubleLit(v)]) => Ok(ExprTree::IntLit(v.floor() as i64)),
+        ("ceil", [ExprTree::DoubleLit(v)]) => Ok(ExprTree::IntLit(v.ceil() as i64)),
+        ("round", [ExprTree::DoubleLit(v)]) => Ok(ExprTree::IntLit(v.round() as i64)),
+        ("sqrt", [ExprTree::DoubleLit(v)]) => Ok(ExprTree::DoubleLit(v.sqrt())),
+        ("range", [ExprTree::IntLit(a), ExprTree::IntLit(b)]) =>
+            Ok(ExprTree::ArrayLit((*a..*b).map(|x| node(ExprTree::IntLit(x))).collect(), Some(Type::Int))),
+        ("concat", [ExprTree::ArrayLit(v, t), ExprTree::ArrayLit(v2, t2)]) =>
+            Ok(ExprTree::ArrayLit([v.clone(), v2.clone()].concat(), t.clone().or(t2.clone()))),
+        ("substring", [s, ExprTree::IntLit(from), ExprTree::IntLit(to)]) => {
+            // Bounds are clamped like in slices.
+            let chars: Vec<char> = as_string(s).ok_or("Type mismatch in call of 'substring'")?.chars().collect();
+            let from = (*from).clamp(0, chars.len() as i64) as usize;
+            let to = (*to).clamp(from as i64, chars.len() as i64) as usize;
+            Ok(string_value(&chars[from..to].iter().collect::<String>()))
+        }, ("toUpper" | "toLower" | "parseInt" | "parseDouble", [s]) => {
+            let s = as_string(s).ok_or(format!["Type mismatch in call of standard function '{}'", name])?;
+            Ok(match name {
+                "toUpper" => string_value(&s.to_uppercase()),
+                "toLower" => string_value(&s.to_lowercase()),
+                "parseInt" => match s.trim().parse::<i64>() {
+                    Ok(v) => ExprTree::JustLit(node(ExprTree::IntLit(v))),
+                    Err(_) => ExprTree::NothingLit(Type::Int)
+                },
+                _ => match s.trim().parse::<f64>() {
+                    Ok(v) => ExprTree::JustLit(node(ExprTree::DoubleLit(v))),
+                    Err(_) => ExprTree::NothingLit(Type::Double)
+                }
+            })
+        }, ("contains" | "startsWith" | "endsWith" | "split", [s, s2]) => {
+            let (s, s2) = match (as_string(s), as_string(s2)) {
+                (Some(s), Some(s2)) => (s, s2),
+                _ => return Err(format!["Type mismatch in call of standard function '{}'", name])
+            };
+            Ok(match name {
+                "contains" => ExprTree::BoolLit(s.contains(&s2)),
+                "startsWith" => ExprTree::BoolLit(s.starts_with(&s2)),
+                "endsWith" => ExprTree::BoolLit(s.ends_with(&s2)),
+                _ => {
+                    if s2.is_empty() {
+                        return Err("Empty separator in call of standard function 'split'".to_string());
+                    }
+                    ExprTree::ArrayLit(s.split(&s2).map(|x| node(string_value(x))).collect(), Some(string_type()))
+                }
+            })
+        },
+        _ => Err(format!["Type mismatch in call of standard function '{}'", name])
+    }
+}
@@ -3,7 +3,9 @@ use std::collections::HashMap;
 #[derive(Debug, Clone)]
 pub enum ExprTree {
     Ref(String, String, Box<Node>, u64, u64),
-    For(String, Box<Lambda>),
+    For(Vec<String>, Box<Lambda>, Box<ForClauses>), // The lambda takes an object of each entity.
+    // Groups the rows of an entity by the key lambda; each (field, aggregate, argument) adds a field to the group records.
+    Group(String, Box<Lambda>, Vec<(String, String, Option<Box<Lambda>>)>),
     IntLit(i64),
     CharLit(char),
     BoolLit(bool),
@@ -24,7 +26,28 @@ pub enum ExprTree {
     Eq(bool, Box<Node>, Box<Node>), // The first arg is true, when == and false when !=
     Cmp(bool, bool, Box<Node>, Box<Node>), // 1st arg: true -> ">", false -> "<", 2nd arg: true -> nonstrict, false -> strict.
     IfExpr(Box<Node>, Box<Node>, Box<Node>),
-    LambdaExpr(Box<Lambda>)
+    LambdaExpr(Box<Lambda>),
+    And(Box<Node>, Box<Node>),
+    Or(Box<Node>, Box<Node>),
+    Not(Box<Node>),
+    Neg(Box<Node>),
+    Let(Vec<(String, Box<Node>)>, Box<Node>),
+    Match(Box<Node>, Vec<(Pattern, Box<Node>)>),
+    SumVal(Type, u64, Box<Node>), // Injection into the given Sum type at the given 0-based variant.
+    StdFunc(String),
+    Index(Box<Node>, Box<Node>),
+    Slice(Box<Node>, Option<Box<Node>>, Option<Box<Node>>),
+    RecordLit(Vec<(String, Box<Node>)>), // Fields are kept sorted by name.
+    Closure(Box<Lambda>, Vec<(String, Box<Node>)>) // A lambda value with the bindings visible where it was created.
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Pattern {
+    Just(String),
+    Nothing,
+    Typed(String, Type), // Selects the variant of a Sum value having the given type.
+    Variant(u64, String), // Selects the variant of a Sum value at the given 0-based index.
+    Wildcard
 }
 
 #[derive(Debug, Clone)]
@@ -41,7 +64,21 @@ impl Node {
 pub struct Lambda {
     pub params: Vec<(String, Type)>,
     pub code: Node,
-    pub named: Option<(String, Type)>
+    pub named: Option<(String, Type)>,
+    pub tvars: Vec<String>, // Type variables introduced by '<T, ...>'.
+    pub ret: Option<Type> // Type of the body, recorded when the lambda is type checked.
+}
+
+// Optional clauses of 'for'. The sort key lambda takes the same parameters as the main one.
+#[derive(Debug, Clone)]
+pub struct ForClauses {
+    pub order: Option<(Lambda, bool)>, // True when descending.
+    pub limit: Option<Node>,
+    pub offset: Option<Node>
+}
+impl ForClauses {
+    pub fn new() -> Self { ForClauses { order: None, limit: None, offset: None }}
+    pub fn is_empty(&self) -> bool { self.order.is_none() && self.limit.is_none() && self.offset.is_none() }
 }
 
 #[derive(Debug, Clone)]
@@ -67,7 +104,11 @@ pub enum Type {
     Array(Box<Type>),
     Maybe(Box<Type>),
     Tuple(Vec<Box<Type>>),
-    Sum(Vec<Box<Type>>)
+    Sum(Vec<Box<Type>>),
+    Record(Vec<(String, Box<Type>)>), // Fields are kept sorted by name.
+    Alias(CoordStr), // Replaced by the aliased type before the command is run.
+    Var(String), // Type variable; names starting with '?' are only used during unification.
+    Forall(Vec<String>, Box<Type>) // Type of a polymorphic lambda.
 }
 #[derive(Debug, Clone)]
 pub enum SpType {
@@ -112,9 +153,20 @@ pub enum Command {
     Reshape(String, Vec<ReshapeOptions>, Option<String>),
     Project(String, Vec<String>, String),
     Join(Vec<String>, Lambda, String),
-    Product(Vec<String>, String),
-    Drop(String)
+    Product(Vec<String>, String, u64),
+    Drop(String),
+    SetGlobal(String, String, Node),
+    TypeAlias(String, SpType),
+    Def(Lambda),
+    SetOp(SetOperation, String, String, String)
+}
+#[derive(Debug, PartialEq)]
+pub enum SetOperation {
+    Union,
+    Intersect,
+    Minus
 }
+pub const DEFAULT_PRODUCT_LIMIT: u64 = 1000000;
 #[derive(Debug)]
 pub struct ComNode {
     pub cmd: Command,
@@ -125,7 +177,10 @@ pub struct ComNode {
 pub struct DBState {
     pub header: Vec<(String, Vec<Attr>)>,
     pub data: HashMap<(u64, u64), Vec<Node>>,
-    pub ref_list: HashMap<(u64, u64), u64>
+    pub ref_list: HashMap<(u64, u64), u64>,
+    pub globals: HashMap<(u64, u64), Node>,
+    pub types: Vec<(String, SpType)>,
+    pub funcs: Vec<Lambda> // Always named.
 }
 
 pub fn escapes(c: &char) -> Option<char> {
@@ -236,7 +291,7 @@ impl Iterator for Lexer {
                 self.index += 1;
                 loop {
                     if let Some(c) = self.input.chars().nth(self.index) {
-                        if c.is_alphanumeric() {
+                        if c.is_alphanumeric() || c == '_' {
                             tok.push(c);
                             self.index += 1;
                             self.col += 1;
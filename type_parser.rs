@@ -1,30 +1,57 @@
 use crate::tree::{self, expect, read_ident, LexerIterator, SpType};
 
+fn parse_tvars<T: LexerIterator>(input: &mut T) -> Result<Vec<String>, String> {
+    let mut tvars = vec![];
+    if input.lookahead() != Some("<".to_string()) {
+        return Ok(tvars);
+    }
+    expect(input, vec!["<"])?;
+    loop {
+        let (ln, cl) = input.coords();
+        let v = read_ident(input)?;
+        if tvars.contains(&v) {
+            return Err(format!["Duplicate type variable '{}' at ({}, {}).", v, ln, cl]);
+        }
+        tvars.push(v);
+        match input.lookahead() {
+            Some(lexeme) if lexeme == ">" => break,
+            Some(_) => expect(input, vec![","])?,
+            None => return Err(format!["Expected '>', found EOF."])
+        }
+    }
+    expect(input, vec![">"])?;
+    Ok(tvars)
+}
+
+// A parameter without a type annotation gets the type 'Var("_")', which is inferred from the context.
+fn parse_param<T: LexerIterator>(input: &mut T) -> Result<(String, tree::Type), String> {
+    let id = read_ident(input)?;
+    if input.lookahead() != Some(":".to_string()) {
+        return Ok((id, tree::Type::Var("_".to_string())));
+    }
+    expect(input, vec![":"])?;
+    Ok((id, tree::Type::parse(input)?))
+}
+
 impl tree::Lambda {
     pub fn parse_lambda<T: LexerIterator>(input: &mut T) -> Result<tree::Lambda, String> {
-        let mut named;
-        match input.next() {
-            Some(a) if a.0.len() != 0 && a.0.chars().nth(0).unwrap().is_alphabetic() => {
-                named = Some((a.0, tree::Type::Bool));
-                expect(input, vec!["("])?;
-            }, Some(a) if a.0 == "(" => {
-                named = None;
-            }, _ => return Err(format!["Expected '(', found EOF."])
+        let mut named = None;
+        match input.lookahead() {
+            Some(a) if a.len() != 0 && a.chars().nth(0).unwrap().is_alphabetic() => {
+                named = Some((read_ident(input)?, tree::Type::Bool));
+            }, Some(_) => {},
+            None => return Err(format!["Expected '(', found EOF."])
         };
+        let tvars = parse_tvars(input)?;
+        expect(input, vec!["("])?;
         let mut paramvec: Vec<(String, tree::Type)> = vec![];
-        let id = read_ident(input)?;
-        expect(input, vec![":"])?;
-        let t = tree::Type::parse(input)?;
-        paramvec.push((id, t));
+        paramvec.push(parse_param(input)?);
         loop {
             match input.lookahead() {
                 Some(lexeme) => {
                     if lexeme == ")" { break; }
                     expect(input, vec![","])?;
-                    let id = read_ident(input)?;
-                    expect(input, vec![":"])?;
-                    let t = tree::Type::parse(input)?;
-                    paramvec.push((id, t));
+                    paramvec.push(parse_param(input)?);
                 },
                 None => return Err(format!["Expected ')', found EOF."])
             }
@@ -36,7 +63,7 @@ impl tree::Lambda {
         expect(input, vec!["{"])?;
         let (val, br1, br2) = tree::find_bracket(input, "}", "{")?;
         return Ok(tree::Lambda{ params: paramvec, named: named, code: 
-            tree::Node::new(val, br1, br2)?});
+            tree::Node::new(val, br1, br2)?, tvars: tvars, ret: None});
     }
 }
 impl tree::SpType {
@@ -54,7 +81,9 @@ impl tree::SpType {
                 return Ok(tree::SpType::Reg(types.swap_remove(i)));
             }
         }
-        if lexeme == "Func" {
+        if lexeme == "String" {
+            return Ok(SpType::Reg(tree::Type::Array(Box::new(tree::Type::Char))));
+        } else if lexeme == "Func" {
             expect(input, vec!["("])?;
             let args = tree::Type::parse_vectype(input)?;
             if args.len() < 2 {
@@ -115,11 +144,32 @@ impl tree::SpType {
                     Ok(SpType::Restrict(Box::new(t), lm))
                 }, _ => Err(format!["Special type not allowed at ({}, {}).", ln, cl])
             };
+        } else if lexeme == "Record" {
+            expect(input, vec!["{"])?;
+            let mut fields: Vec<(String, Box<tree::Type>)> = vec![];
+            loop {
+                let name = read_ident(input)?;
+                if fields.iter().any(|x| x.0 == name) {
+                    return Err(format!["Duplicate field '{}' in record type at ({}, {}).", name, line, col]);
+                }
+                expect(input, vec![":"])?;
+                fields.push((name, Box::new(tree::Type::parse(input)?)));
+                match input.lookahead() {
+                    Some(lexeme) if lexeme == "}" => break,
+                    Some(_) => expect(input, vec![","])?,
+                    None => return Err(format!["Expected '}}', found EOF."])
+                }
+            }
+            expect(input, vec!["}"])?;
+            fields.sort_by(|x, y| x.0.cmp(&y.0));
+            return Ok(SpType::Reg(tree::Type::Record(fields)));
         } else if lexeme == "Object" {
             expect(input, vec!["("])?;
             let s = read_ident(input)?;
             expect(input, vec![")"])?;
             return Ok(SpType::Reg(tree::Type::Object(tree::CoordStr{ name: s, ln: line, col: col })));
+        } else if lexeme.chars().nth(0).map_or(false, |c| c.is_alphabetic()) {
+            return Ok(SpType::Reg(tree::Type::Alias(tree::CoordStr{ name: lexeme, ln: line, col: col })));
         }
         return Err(format!["Unrecognized type '{}' at ({}, {})", lexeme, line, col]);
     }